@@ -0,0 +1,160 @@
+use encoding_rs::{UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+// Tried in this order, so a tie goes to the comma
+const CANDIDATE_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CsvEncoding {
+    #[default]
+    Utf8,
+    // What Excel needs to open a UTF-8 file without mangling accents
+    Utf8Bom,
+    // What Windows PowerShell's Export-Csv -Encoding Unicode produces
+    Utf16Le,
+}
+
+// How CSV inventories are written. Reading detects all of this on its own.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CsvDialect {
+    pub delimiter: char,
+    pub encoding: CsvEncoding,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        CsvDialect {
+            delimiter: ',',
+            encoding: CsvEncoding::Utf8,
+        }
+    }
+}
+
+impl CsvDialect {
+    pub fn delimiter_byte(&self) -> Result<u8, String> {
+        if self.delimiter.is_ascii() && !matches!(self.delimiter, '"' | '\r' | '\n') {
+            Ok(self.delimiter as u8)
+        } else {
+            Err(format!("'{}' cannot be used as a CSV delimiter", self.delimiter.escape_default()))
+        }
+    }
+}
+
+// Turn the raw bytes of a CSV file into text. A BOM decides the encoding when
+// there is one; otherwise UTF-16 is recognised by its zero bytes, and anything
+// that isn't valid UTF-8 is taken to be the Windows ANSI code page.
+pub fn decode(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return String::from_utf8_lossy(rest).into_owned();
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        return UTF_16LE.decode_without_bom_handling(rest).0.into_owned();
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+        return UTF_16BE.decode_without_bom_handling(rest).0.into_owned();
+    }
+
+    // ASCII text in UTF-16 has a zero in every other byte
+    let sample = &bytes[..bytes.len().min(512)];
+    let zeros_at = |offset: usize| sample.iter().skip(offset).step_by(2).filter(|b| **b == 0).count();
+    let pairs = sample.len() / 2;
+    if pairs > 0 {
+        if zeros_at(1) * 2 > pairs {
+            return UTF_16LE.decode_without_bom_handling(bytes).0.into_owned();
+        }
+        if zeros_at(0) * 2 > pairs {
+            return UTF_16BE.decode_without_bom_handling(bytes).0.into_owned();
+        }
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => WINDOWS_1252.decode_without_bom_handling(bytes).0.into_owned(),
+    }
+}
+
+// Pick the delimiter from the header line: whichever candidate splits it into
+// the most columns, ignoring anything inside quotes
+pub fn detect_delimiter(text: &str) -> u8 {
+    let header = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+
+    let count_outside_quotes = |delimiter: u8| {
+        let mut in_quotes = false;
+        header
+            .bytes()
+            .filter(|&b| {
+                if b == b'"' {
+                    in_quotes = !in_quotes;
+                }
+                !in_quotes && b == delimiter
+            })
+            .count()
+    };
+
+    let mut best = (b',', 0);
+    for delimiter in CANDIDATE_DELIMITERS {
+        let count = count_outside_quotes(delimiter);
+        if count > best.1 {
+            best = (delimiter, count);
+        }
+    }
+    best.0
+}
+
+pub fn encode(text: &str, encoding: CsvEncoding) -> Vec<u8> {
+    match encoding {
+        CsvEncoding::Utf8 => text.as_bytes().to_vec(),
+        CsvEncoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+        CsvEncoding::Utf16Le => {
+            let mut bytes = UTF16LE_BOM.to_vec();
+            bytes.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+            bytes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_every_encoding_it_writes() {
+        let text = "hostname,description\r\nsrv01,Café\r\n";
+        for encoding in [CsvEncoding::Utf8, CsvEncoding::Utf8Bom, CsvEncoding::Utf16Le] {
+            assert_eq!(decode(&encode(text, encoding)), text, "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn decodes_files_without_a_bom() {
+        let utf16le: Vec<u8> = "hostname\r\nsrv01".encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        assert_eq!(decode(&utf16le), "hostname\r\nsrv01");
+        let utf16be: Vec<u8> = "hostname\r\nsrv01".encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(decode(&utf16be), "hostname\r\nsrv01");
+        // "Café" saved by Excel in the ANSI code page
+        assert_eq!(decode(b"Caf\xe9"), "Café");
+    }
+
+    #[test]
+    fn detects_the_delimiter_from_the_header() {
+        assert_eq!(detect_delimiter("hostname;description;group\nsrv01;a,b;c"), b';');
+        assert_eq!(detect_delimiter("hostname\tdescription\n"), b'\t');
+        assert_eq!(detect_delimiter("\n\nhostname|description|group"), b'|');
+        assert_eq!(detect_delimiter("\"host;name\",description"), b',');
+        assert_eq!(detect_delimiter("hostname"), b',');
+    }
+
+    #[test]
+    fn refuses_delimiters_that_break_csv() {
+        let dialect = |delimiter| CsvDialect { delimiter, ..CsvDialect::default() };
+        assert_eq!(dialect(';').delimiter_byte(), Ok(b';'));
+        assert!(dialect('"').delimiter_byte().is_err());
+        assert!(dialect('\n').delimiter_byte().is_err());
+        assert!(dialect('§').delimiter_byte().is_err());
+    }
+}
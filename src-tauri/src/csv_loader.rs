@@ -0,0 +1,511 @@
+use crate::csv_dialect::{self, CsvDialect};
+use crate::rdp::RdpSettings;
+use crate::{inventory, validation, Host};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+// For JSON and YAML inventories `line` is the position of the entry in the
+// hosts list, counting from 1
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CsvProblem {
+    pub line: u64,
+    pub reason: String,
+}
+
+// A row or entry that wasn't loaded, kept as it was so writing the inventory
+// back doesn't throw it away
+#[derive(Debug, Clone)]
+pub enum UnparsedEntry {
+    // A CSV row, columns in HOSTS_CSV_HEADER order when they could be mapped
+    Row(Vec<String>),
+    // An entry of a JSON or YAML hosts list
+    Value(Value),
+}
+
+impl UnparsedEntry {
+    pub fn to_row(&self) -> Vec<String> {
+        match self {
+            UnparsedEntry::Row(row) => row.clone(),
+            UnparsedEntry::Value(value) => HOSTS_CSV_HEADER.iter().map(|name| cell_text(value.get(name))).collect(),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            UnparsedEntry::Value(value) => value.clone(),
+            UnparsedEntry::Row(row) => Value::Object(
+                HOSTS_CSV_HEADER
+                    .iter()
+                    .zip(row)
+                    .filter(|(_, cell)| !cell.is_empty())
+                    .map(|(name, cell)| (name.to_string(), Value::String(cell.clone())))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// A JSON value as a CSV cell, in the same notation the columns use
+fn cell_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Bool(flag)) => if *flag { "yes" } else { "" }.to_string(),
+        Some(Value::Array(items)) => items.iter().map(|item| cell_text(Some(item))).collect::<Vec<_>>().join("; "),
+        Some(Value::Object(map)) => map
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, cell_text(Some(value))))
+            .collect::<Vec<_>>()
+            .join("; "),
+        Some(other) => other.to_string(),
+    }
+}
+
+// Everything that could be loaded from a hosts file, plus a line-numbered
+// list of the rows that couldn't
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub hosts: Vec<Host>,
+    pub problems: Vec<CsvProblem>,
+    // The rows behind the problems, written back with the hosts
+    pub unparsed: Vec<UnparsedEntry>,
+    // Rows too malformed to keep even as they were
+    pub unreadable: usize,
+    seen: HashSet<String>,
+}
+
+impl LoadReport {
+    // Validate a host read from `line` and keep it, unless it is invalid or
+    // repeats a hostname that was already loaded. `raw` is what the host was
+    // read from, kept instead when the host isn't.
+    pub fn push_host(&mut self, line: u64, host: Host, raw: impl FnOnce() -> UnparsedEntry) {
+        let host = match validation::normalize_host(&host) {
+            Ok(host) => host,
+            Err(errors) => {
+                self.push_problem(line, validation::describe(&errors), raw());
+                return;
+            }
+        };
+
+        if !self.seen.insert(host.hostname.to_lowercase()) {
+            let reason = format!("Duplicate hostname {}, only the first entry is loaded", host.hostname);
+            self.push_problem(line, reason, raw());
+            return;
+        }
+
+        self.hosts.push(host);
+    }
+
+    pub fn push_problem(&mut self, line: u64, reason: String, raw: UnparsedEntry) {
+        self.problems.push(CsvProblem { line, reason });
+        self.unparsed.push(raw);
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HostsFileReport {
+    path: String,
+    valid_hosts: usize,
+    problems: Vec<CsvProblem>,
+}
+
+// Which column holds each field. Files written before group, port and
+// gateway existed only have the first two.
+pub struct ColumnMap {
+    pub hostname: usize,
+    pub description: Option<usize>,
+    pub group: Option<usize>,
+    pub port: Option<usize>,
+    pub gateway: Option<usize>,
+    pub tags: Option<usize>,
+    pub fields: Option<usize>,
+    pub os: Option<usize>,
+    pub aliases: Option<usize>,
+    pub favorite: Option<usize>,
+    pub rdp: Option<usize>,
+}
+
+impl ColumnMap {
+    // Column of each field in HOSTS_CSV_HEADER order
+    fn in_header_order(&self) -> [Option<usize>; HOSTS_CSV_HEADER.len()] {
+        [
+            Some(self.hostname),
+            self.description,
+            self.group,
+            self.port,
+            self.gateway,
+            self.tags,
+            self.fields,
+            self.os,
+            self.aliases,
+            self.favorite,
+            self.rdp,
+        ]
+    }
+
+    const POSITIONAL: ColumnMap = ColumnMap {
+        hostname: 0,
+        description: Some(1),
+        group: Some(2),
+        port: Some(3),
+        gateway: Some(4),
+        tags: Some(5),
+        fields: Some(6),
+        os: Some(7),
+        aliases: Some(8),
+        favorite: Some(9),
+        rdp: Some(10),
+    };
+
+    // Match headers by name so columns can come in any order, including the
+    // names AD and other tools use ("DNSHostName", "Name", "Comment").
+    // None when there is no recognisable hostname column.
+    pub fn from_headers<'a>(headers: impl IntoIterator<Item = &'a str>) -> Option<ColumnMap> {
+        let names: Vec<String> = headers
+            .into_iter()
+            .map(|header| header.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase())
+            .collect();
+        // Aliases are in order of preference, AD exports have both Name and DNSHostName
+        let find = |aliases: &[&str]| aliases.iter().find_map(|alias| names.iter().position(|name| name == alias));
+
+        let hostname = find(&["hostname", "dnshostname", "host", "computername", "computer", "server", "name", "address"])?;
+        Some(ColumnMap {
+            hostname,
+            description: find(&["description", "desc", "comment", "comments", "notes"]),
+            group: find(&["group", "folder", "path"]),
+            port: find(&["port", "rdpport"]),
+            gateway: find(&["gateway", "rdgateway", "gatewayhostname"]),
+            tags: find(&["tags", "tag", "labels"]),
+            fields: find(&["fields", "customfields"]),
+            os: find(&["os", "operatingsystem"]),
+            aliases: find(&["aliases", "alias", "cnames"]),
+            favorite: find(&["favorite", "favourite", "starred", "pinned"]),
+            rdp: find(&["rdp", "rdpsettings"]),
+        })
+    }
+}
+
+// Load every valid row of a hosts CSV. Bad rows are reported and set aside
+// instead of failing the whole file; only an unreadable file is an error.
+// Encoding, BOM and delimiter are detected, so exports from PowerShell or a
+// regional Excel load as they are.
+pub fn load_hosts_csv(path: &Path) -> Result<LoadReport, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read CSV: {}", e))?;
+    let contents = csv_dialect::decode(&bytes);
+
+    let mut report = LoadReport::default();
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .delimiter(csv_dialect::detect_delimiter(&contents))
+        .from_reader(contents.as_bytes());
+
+    let columns = match reader.headers() {
+        // Headers we don't recognise at all fall back to the column order
+        Ok(headers) => ColumnMap::from_headers(headers).unwrap_or(ColumnMap::POSITIONAL),
+        Err(e) => return Err(format!("Failed to read CSV header: {}", e)),
+    };
+
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or(0);
+                report.problems.push(CsvProblem { line, reason: format!("Malformed record: {}", e) });
+                report.unreadable += 1;
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        if record.get(columns.hostname).is_none() {
+            // Nothing to map the columns by, so keep the row exactly as it is
+            report.push_problem(
+                line,
+                format!("Expected a hostname in column {}, found {} field(s)", columns.hostname + 1, record.len()),
+                UnparsedEntry::Row(record.iter().map(str::to_string).collect()),
+            );
+            continue;
+        }
+
+        let field = |idx: Option<usize>| idx.and_then(|i| record.get(i)).unwrap_or("").to_string();
+        let raw = || UnparsedEntry::Row(columns.in_header_order().iter().map(|&idx| field(idx)).collect());
+        let port = match field(columns.port).trim() {
+            "" => None,
+            value => match value.parse::<u16>() {
+                Ok(port) => Some(port),
+                Err(_) => {
+                    report.push_problem(line, format!("Invalid port '{}'", value), raw());
+                    continue;
+                }
+            },
+        };
+
+        let rdp = match split_rdp(&field(columns.rdp)) {
+            Ok(rdp) => rdp,
+            Err(e) => {
+                report.push_problem(line, e, raw());
+                continue;
+            }
+        };
+
+        let host = Host {
+            hostname: field(Some(columns.hostname)),
+            description: field(columns.description),
+            group: field(columns.group),
+            port,
+            gateway: field(columns.gateway),
+            aliases: split_tags(&field(columns.aliases)),
+            os: field(columns.os),
+            tags: split_tags(&field(columns.tags)),
+            fields: split_fields(&field(columns.fields)),
+            favorite: parse_flag(&field(columns.favorite)),
+            rdp,
+        };
+
+        report.push_host(line, host, raw);
+    }
+
+    Ok(report)
+}
+
+// Tags and aliases each share a single column, separated by semicolons
+pub fn split_tags(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Custom fields share a column too, as "site=LON; role=SQL"
+pub fn split_fields(value: &str) -> BTreeMap<String, String> {
+    value
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+// Favorites are written as "yes", spreadsheets tend to have their own idea
+pub fn parse_flag(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "yes" | "y" | "true" | "1" | "x")
+}
+
+// Per-host RDP settings use the same notation, e.g. "full_screen=false;
+// desktop_width=1920"
+pub fn split_rdp(value: &str) -> Result<RdpSettings, String> {
+    let settings: serde_json::Map<String, Value> = split_fields(value)
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value.to_lowercase().as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => value.parse::<u64>().map(Value::from).unwrap_or(Value::String(value)),
+            };
+            (key, value)
+        })
+        .collect();
+    serde_json::from_value(Value::Object(settings)).map_err(|e| format!("Invalid RDP settings '{}': {}", value, e))
+}
+
+pub fn join_rdp(rdp: &RdpSettings) -> String {
+    match serde_json::to_value(rdp) {
+        Ok(Value::Object(settings)) => settings
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => String::new(),
+    }
+}
+
+pub fn join_fields(fields: &BTreeMap<String, String>) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub const HOSTS_CSV_HEADER: [&str; 11] =
+    ["hostname", "description", "group", "port", "gateway", "tags", "fields", "os", "aliases", "favorite", "rdp"];
+
+// Rows that couldn't be loaded are written after the hosts, as they were
+pub fn hosts_to_csv(hosts: &[Host], unparsed: &[UnparsedEntry], dialect: &CsvDialect) -> Result<Vec<u8>, String> {
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(dialect.delimiter_byte()?)
+        // Short rows kept from the file may have fewer columns
+        .flexible(true)
+        .from_writer(Vec::new());
+
+    // Write header
+    wtr.write_record(HOSTS_CSV_HEADER)
+        .map_err(|e| format!("Failed to write CSV header: {}", e))?;
+
+    // Write records
+    for host in hosts {
+        let port = host.port.map(|p| p.to_string()).unwrap_or_default();
+        let tags = host.tags.join("; ");
+        let fields = join_fields(&host.fields);
+        let aliases = host.aliases.join("; ");
+        let favorite = if host.favorite { "yes" } else { "" }.to_string();
+        let rdp = join_rdp(&host.rdp);
+        wtr.write_record([
+            &host.hostname,
+            &host.description,
+            &host.group,
+            &port,
+            &host.gateway,
+            &tags,
+            &fields,
+            &host.os,
+            &aliases,
+            &favorite,
+            &rdp,
+        ])
+            .map_err(|e| format!("Failed to write CSV record: {}", e))?;
+    }
+    for entry in unparsed {
+        wtr.write_record(entry.to_row())
+            .map_err(|e| format!("Failed to write CSV record: {}", e))?;
+    }
+
+    let contents = wtr.into_inner()
+        .map_err(|e| format!("Failed to flush CSV writer: {}", e))?;
+    let contents = String::from_utf8(contents)
+        .map_err(|e| format!("Failed to encode CSV: {}", e))?;
+
+    Ok(csv_dialect::encode(&contents, dialect.encoding))
+}
+
+// Check a hosts file without loading it, so problems can be fixed first.
+// Defaults to the inventory itself.
+#[tauri::command]
+pub fn validate_hosts_file(path: Option<String>) -> Result<HostsFileReport, String> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            // No inventory yet on first run, which is nothing to report
+            let path = inventory::path();
+            if !path.exists() {
+                return Ok(HostsFileReport { path: path.display().to_string(), valid_hosts: 0, problems: Vec::new() });
+            }
+            path.display().to_string()
+        }
+    };
+    let report = inventory::load(Path::new(&path))?;
+
+    Ok(HostsFileReport {
+        path,
+        valid_hosts: report.hosts.len(),
+        problems: report.problems,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_dialect::CsvEncoding;
+    use crate::test_support::{host, strings, temp_path};
+
+    fn load_bytes(name: &str, bytes: &[u8]) -> LoadReport {
+        let path = temp_path(&format!("{}.csv", name));
+        std::fs::write(&path, bytes).unwrap();
+        let report = load_hosts_csv(&path);
+        std::fs::remove_file(&path).unwrap();
+        report.unwrap()
+    }
+
+    #[test]
+    fn round_trips_in_every_dialect() {
+        let hosts = vec![
+            Host {
+                description: "Primary; \"SQL\", London".to_string(),
+                port: Some(3390),
+                tags: strings(&["prod", "sql"]),
+                ..host("lon-sql01")
+            },
+            Host { description: "Café|tab\there".to_string(), ..host("ams-web01") },
+        ];
+        for delimiter in [',', ';', '\t', '|'] {
+            for encoding in [CsvEncoding::Utf8, CsvEncoding::Utf8Bom, CsvEncoding::Utf16Le] {
+                let dialect = CsvDialect { delimiter, encoding };
+                let bytes = hosts_to_csv(&hosts, &[], &dialect).unwrap();
+                let report = load_bytes(&format!("dialect-{}-{:?}", delimiter as u32, encoding), &bytes);
+                assert!(report.problems.is_empty(), "{:?}: {:?}", dialect, report.problems);
+                assert_eq!(report.hosts, hosts, "{:?}", dialect);
+            }
+        }
+    }
+
+    #[test]
+    fn maps_columns_by_header_name() {
+        let report = load_bytes(
+            "ad-export",
+            "Name;Description;OperatingSystem;DNSHostName;Port\nLON-SQL01;SQL node;Windows Server 2019;lon-sql01.corp;3390\n"
+                .as_bytes(),
+        );
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        let expected = Host {
+            description: "SQL node".to_string(),
+            port: Some(3390),
+            os: "Windows Server 2019".to_string(),
+            ..host("lon-sql01.corp")
+        };
+        assert_eq!(report.hosts, vec![expected]);
+    }
+
+    #[test]
+    fn reads_older_and_unrecognised_headers_by_position() {
+        let old = load_bytes("two-columns", b"hostname,description\nsrv01,File server\n");
+        assert_eq!(old.hosts, vec![Host { description: "File server".to_string(), ..host("srv01") }]);
+
+        let unknown = load_bytes("unknown", b"a,b,c,d\nsrv02,Print server,,3391\n");
+        assert_eq!(unknown.hosts, vec![Host { description: "Print server".to_string(), port: Some(3391), ..host("srv02") }]);
+    }
+
+    #[test]
+    fn reads_favorites_from_any_yes_like_value() {
+        let report = load_bytes("favourites", b"hostname,favourite\nsrv01,Yes\nsrv02,x\nsrv03,\nsrv04,no\n");
+        let starred: Vec<bool> = report.hosts.iter().map(|h| h.favorite).collect();
+        assert_eq!(starred, [true, true, false, false]);
+
+        let bytes = hosts_to_csv(&report.hosts, &[], &CsvDialect::default()).unwrap();
+        assert_eq!(load_bytes("favourites-written", &bytes).hosts, report.hosts);
+    }
+
+    #[test]
+    fn keeps_rows_that_fail_to_load() {
+        let report = load_bytes("bad-rows", b"hostname,description,group,port\nsrv01,ok,,\nsrv02,bad port,,abc\nsrv01,duplicate,,\n");
+        assert_eq!(report.hosts, vec![Host { description: "ok".to_string(), ..host("srv01") }]);
+        assert_eq!(report.problems.len(), 2);
+
+        let bytes = hosts_to_csv(&report.hosts, &report.unparsed, &CsvDialect::default()).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.contains("srv02,bad port,,abc"), "{}", text);
+        assert!(text.contains("srv01,duplicate"), "{}", text);
+    }
+
+    #[test]
+    fn reports_problems_with_their_line_numbers() {
+        let report = load_bytes("line-numbers", b"hostname,description,group,port\nsrv01,ok,,\n,no hostname,,\nsrv03,ok,,\nsrv04,bad port,,99999\n");
+        let lines: Vec<u64> = report.problems.iter().map(|p| p.line).collect();
+        assert_eq!(lines, [3, 5]);
+        assert_eq!(report.hosts.len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_rdp_settings() {
+        let report = load_bytes("bad-rdp", b"hostname,rdp\nsrv01,desktop_width=wide\n");
+        assert!(report.hosts.is_empty());
+        assert_eq!(report.problems.len(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::Host;
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct HostsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl HostsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+// Compare two host lists by hostname and report what changed between them
+pub fn diff_hosts(old: &[Host], new: &[Host]) -> HostsDiff {
+    let old_by_name: HashMap<&str, &Host> = old.iter().map(|h| (h.hostname.as_str(), h)).collect();
+    let new_by_name: HashMap<&str, &Host> = new.iter().map(|h| (h.hostname.as_str(), h)).collect();
+    let mut diff = HostsDiff::default();
+
+    for host in new {
+        match old_by_name.get(host.hostname.as_str()) {
+            Some(existing) if *existing != host => diff.modified.push(host.hostname.clone()),
+            Some(_) => {}
+            None => diff.added.push(host.hostname.clone()),
+        }
+    }
+
+    for host in old {
+        if !new_by_name.contains_key(host.hostname.as_str()) {
+            diff.removed.push(host.hostname.clone());
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host;
+
+    #[test]
+    fn reports_added_removed_and_modified_hosts() {
+        let old = vec![host("srv01"), host("srv02"), host("srv03")];
+        let new = vec![
+            host("srv01"),
+            Host { description: "File server".to_string(), ..host("srv02") },
+            host("srv04"),
+        ];
+
+        let diff = diff_hosts(&old, &new);
+        assert_eq!(diff.added, vec!["srv04"]);
+        assert_eq!(diff.removed, vec!["srv03"]);
+        assert_eq!(diff.modified, vec!["srv02"]);
+        assert!(diff_hosts(&new, &new).is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{get_hosts, history, log_to_file, snapshots, write_hosts, Host};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, ToSocketAddrs};
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DuplicateGroup {
+    hostnames: Vec<String>,
+    // Why the hosts were grouped, e.g. "same short name srv01"
+    reasons: Vec<String>,
+    // Suggested entry to keep, the user can pick another one
+    canonical: String,
+}
+
+// "SRV01.corp.local." -> "srv01"; IP literals are kept whole
+fn short_name(hostname: &str) -> String {
+    let name = hostname.trim().trim_end_matches('.').to_lowercase();
+    if name.parse::<IpAddr>().is_ok() {
+        return name;
+    }
+    name.split('.').next().unwrap_or("").to_string()
+}
+
+pub fn resolve_addresses(hostname: &str) -> Vec<IpAddr> {
+    let name = hostname.trim().trim_end_matches('.');
+    if let Ok(ip) = name.parse::<IpAddr>() {
+        return vec![ip];
+    }
+    (name, 3389)
+        .to_socket_addrs()
+        .map(|addrs| addrs.map(|a| a.ip()).collect())
+        .unwrap_or_default()
+}
+
+// Look up every host, a handful at a time since each lookup can block for a while
+fn resolve_all(hosts: &[Host]) -> Vec<Vec<IpAddr>> {
+    const LOOKUP_THREADS: usize = 16;
+    let chunk_size = hosts.len().div_ceil(LOOKUP_THREADS).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = hosts
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let handle = scope.spawn(move || {
+                    chunk.iter().map(|h| resolve_addresses(&h.hostname)).collect::<Vec<_>>()
+                });
+                (chunk.len(), handle)
+            })
+            .collect();
+
+        // Results have to line up with the hosts, so a failed chunk still
+        // contributes one (empty) entry per host
+        handles
+            .into_iter()
+            .flat_map(|(len, handle)| handle.join().unwrap_or_else(|_| vec![Vec::new(); len]))
+            .collect()
+    })
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        UnionFind { parent: (0..size).collect() }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+        }
+    }
+}
+
+// Prefer a fully qualified name, then the entry with the most to say about itself
+fn suggest_canonical(hosts: &[&Host]) -> String {
+    hosts
+        .iter()
+        .max_by_key(|h| {
+            let is_fqdn = h.hostname.contains('.') && h.hostname.parse::<IpAddr>().is_err();
+            (is_fqdn, h.description.len())
+        })
+        .map(|h| h.hostname.clone())
+        .unwrap_or_default()
+}
+
+// Group hosts that are probably the same machine: same name ignoring case and
+// DNS suffix, or (with resolve_dns) resolving to the same address
+#[tauri::command]
+pub async fn find_duplicate_hosts(resolve_dns: bool) -> Result<Vec<DuplicateGroup>, String> {
+    let hosts = get_hosts()?;
+    let mut groups = UnionFind::new(hosts.len());
+    let mut links: Vec<(usize, usize, String)> = Vec::new();
+
+    let mut by_name: HashMap<String, usize> = HashMap::new();
+    for (i, host) in hosts.iter().enumerate() {
+        let key = short_name(&host.hostname);
+        if key.is_empty() {
+            continue;
+        }
+        match by_name.get(&key) {
+            Some(&first) => links.push((first, i, format!("same short name {}", key))),
+            None => {
+                by_name.insert(key, i);
+            }
+        }
+    }
+
+    if resolve_dns {
+        let mut by_address: HashMap<IpAddr, usize> = HashMap::new();
+        for (i, addresses) in resolve_all(&hosts).into_iter().enumerate() {
+            for address in addresses {
+                match by_address.get(&address) {
+                    Some(&first) => links.push((first, i, format!("both resolve to {}", address))),
+                    None => {
+                        by_address.insert(address, i);
+                    }
+                }
+            }
+        }
+    }
+
+    for (a, b, _) in &links {
+        groups.union(*a, *b);
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..hosts.len() {
+        let root = groups.find(i);
+        members.entry(root).or_default().push(i);
+    }
+
+    let mut reasons: HashMap<usize, BTreeSet<String>> = HashMap::new();
+    for (a, _, reason) in links {
+        let root = groups.find(a);
+        reasons.entry(root).or_default().insert(reason);
+    }
+
+    let mut result: Vec<DuplicateGroup> = members
+        .into_iter()
+        .filter(|(_, indices)| indices.len() > 1)
+        .map(|(root, indices)| {
+            let group_hosts: Vec<&Host> = indices.iter().map(|&i| &hosts[i]).collect();
+            DuplicateGroup {
+                hostnames: group_hosts.iter().map(|h| h.hostname.clone()).collect(),
+                reasons: reasons.remove(&root).unwrap_or_default().into_iter().collect(),
+                canonical: suggest_canonical(&group_hosts),
+            }
+        })
+        .collect();
+
+    result.sort_by_key(|group| group.canonical.to_lowercase());
+    Ok(result)
+}
+
+// Fold the duplicates into the canonical entry. Distinct descriptions,
+// aliases, tags and custom fields are all kept, other fields are only filled
+// in where the canonical entry has nothing set. The duplicates' hostnames
+// become aliases so searching or connecting by them still finds the host.
+fn merge_into(canonical: &Host, others: &[Host]) -> Host {
+    let mut merged = canonical.clone();
+
+    let mut descriptions: Vec<String> = Vec::new();
+    for description in std::iter::once(&merged.description).chain(others.iter().map(|h| &h.description)) {
+        let description = description.trim();
+        if !description.is_empty() && !descriptions.iter().any(|d| d.eq_ignore_ascii_case(description)) {
+            descriptions.push(description.to_string());
+        }
+    }
+    merged.description = descriptions.join(" / ");
+
+    for other in others {
+        if merged.group.is_empty() {
+            merged.group = other.group.clone();
+        }
+        if merged.port.is_none() {
+            merged.port = other.port;
+        }
+        if merged.gateway.is_empty() {
+            merged.gateway = other.gateway.clone();
+        }
+        if merged.os.is_empty() {
+            merged.os = other.os.clone();
+        }
+        merged.favorite |= other.favorite;
+        // Settings made on the canonical entry win
+        merged.rdp = other.rdp.overlay(&merged.rdp);
+
+        for alias in std::iter::once(&other.hostname).chain(&other.aliases) {
+            if !alias.eq_ignore_ascii_case(&merged.hostname)
+                && !merged.aliases.iter().any(|a| a.eq_ignore_ascii_case(alias))
+            {
+                merged.aliases.push(alias.clone());
+            }
+        }
+        for tag in &other.tags {
+            if !merged.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                merged.tags.push(tag.clone());
+            }
+        }
+        for (key, value) in &other.fields {
+            if !merged.fields.keys().any(|k| k.eq_ignore_ascii_case(key)) {
+                merged.fields.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    merged
+}
+
+// Merge the duplicates into the canonical entry and remove them
+#[tauri::command]
+pub fn merge_hosts(canonical: String, duplicates: Vec<String>) -> Result<Host, String> {
+    let mut hosts = get_hosts()?;
+
+    let canonical_host = hosts
+        .iter()
+        .find(|h| h.hostname.eq_ignore_ascii_case(&canonical))
+        .cloned()
+        .ok_or(format!("Host not found: {}", canonical))?;
+
+    let is_duplicate = |hostname: &str| {
+        !hostname.eq_ignore_ascii_case(&canonical_host.hostname)
+            && duplicates.iter().any(|d| d.eq_ignore_ascii_case(hostname))
+    };
+    let others: Vec<Host> = hosts
+        .iter()
+        .filter(|h| is_duplicate(&h.hostname))
+        .cloned()
+        .collect();
+    if others.is_empty() {
+        return Err("No duplicates to merge".to_string());
+    }
+
+    let merged = merge_into(&canonical_host, &others);
+
+    snapshots::create_snapshot("merge")?;
+
+    let before = hosts.clone();
+    hosts.retain(|h| !is_duplicate(&h.hostname));
+    if let Some(host) = hosts.iter_mut().find(|h| h.hostname == canonical_host.hostname) {
+        *host = merged.clone();
+    }
+    write_hosts(&hosts)?;
+    history::record_all("merged", &before, &hosts);
+
+    let merged_names: Vec<&str> = others.iter().map(|h| h.hostname.as_str()).collect();
+    log_to_file(&format!("Merged {} into {}", merged_names.join(", "), canonical_host.hostname));
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdp::RdpSettings;
+    use crate::test_support::{host, strings};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn merging_keeps_everything_the_duplicates_knew() {
+        let canonical = Host {
+            description: "SQL node".to_string(),
+            tags: strings(&["prod"]),
+            fields: BTreeMap::from([("role".to_string(), "SQL".to_string())]),
+            rdp: RdpSettings { full_screen: Some(true), ..RdpSettings::default() },
+            ..host("srv01.corp.local")
+        };
+        let duplicate = Host {
+            description: "sql node".to_string(),
+            aliases: strings(&["sqlcluster"]),
+            os: "Windows Server 2019".to_string(),
+            tags: strings(&["PROD", "sql"]),
+            fields: BTreeMap::from([
+                ("Role".to_string(), "Web".to_string()),
+                ("site".to_string(), "LON".to_string()),
+            ]),
+            rdp: RdpSettings { full_screen: Some(false), desktop_width: Some(1920), ..RdpSettings::default() },
+            ..host("SRV01")
+        };
+
+        let merged = merge_into(&canonical, &[duplicate]);
+        assert_eq!(merged.hostname, "srv01.corp.local");
+        assert_eq!(merged.description, "SQL node");
+        assert_eq!(merged.aliases, strings(&["SRV01", "sqlcluster"]));
+        assert_eq!(merged.os, "Windows Server 2019");
+        assert_eq!(merged.tags, strings(&["prod", "sql"]));
+        assert_eq!(merged.fields.get("role").map(String::as_str), Some("SQL"));
+        assert_eq!(merged.fields.get("site").map(String::as_str), Some("LON"));
+        assert!(!merged.fields.contains_key("Role"));
+        assert_eq!(merged.rdp.full_screen, Some(true));
+        assert_eq!(merged.rdp.desktop_width, Some(1920));
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    folders, get_hosts, get_stored_credentials, log_to_file, profile_credential_target, rdp, read_credential, xlsx, Host,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    RdcMan,
+    MRemoteNg,
+    RdpBundle,
+    Xlsx,
+}
+
+impl ExportFormat {
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "rdcman" | "rdg" => Ok(ExportFormat::RdcMan),
+            "mremoteng" => Ok(ExportFormat::MRemoteNg),
+            "rdp" | "zip" => Ok(ExportFormat::RdpBundle),
+            "xlsx" | "excel" => Ok(ExportFormat::Xlsx),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+// Export the inventory, or just the given hostnames, for use outside ConnectX.
+// Usernames are only written when include_credentials is set; passwords are
+// never exported because both RDCMan and mRemoteNG expect them encrypted with
+// keys that only they hold.
+#[tauri::command]
+pub async fn export_hosts(
+    path: String,
+    format: String,
+    hostnames: Option<Vec<String>>,
+    include_credentials: bool,
+) -> Result<usize, String> {
+    let format = ExportFormat::from_name(&format)?;
+
+    let hosts: Vec<Host> = match hostnames {
+        Some(names) => get_hosts()?
+            .into_iter()
+            .filter(|h| names.contains(&h.hostname))
+            .collect(),
+        None => get_hosts()?,
+    };
+
+    let usernames = if include_credentials {
+        collect_usernames(&hosts).await?
+    } else {
+        HashMap::new()
+    };
+
+    write_export(Path::new(&path), format, &hosts, &usernames)?;
+
+    log_to_file(&format!("Exported {} host(s) to {}", hosts.len(), path));
+    Ok(hosts.len())
+}
+
+fn write_export(
+    path: &Path,
+    format: ExportFormat,
+    hosts: &[Host],
+    usernames: &HashMap<String, String>,
+) -> Result<(), String> {
+    match format {
+        ExportFormat::RdcMan => std::fs::write(path, to_rdcman(&resolve_hosts(hosts)?, usernames))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        ExportFormat::MRemoteNg => std::fs::write(path, to_mremoteng(&resolve_hosts(hosts)?, usernames))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        ExportFormat::RdpBundle => write_rdp_bundle(path, hosts, usernames),
+        ExportFormat::Xlsx => xlsx::write_xlsx(path, hosts, usernames),
+    }
+}
+
+// The managers connect with their own settings, so hand them the gateway and
+// port the host inherits from its folders
+fn resolve_hosts(hosts: &[Host]) -> Result<Vec<Host>, String> {
+    Ok(folders::resolve_hosts(hosts)?.into_iter().map(|resolved| resolved.host).collect())
+}
+
+// The username launch_rdp would log on with: the folder's credential profile
+// when it has one saved, the default credentials otherwise
+async fn collect_usernames(hosts: &[Host]) -> Result<HashMap<String, String>, String> {
+    let default_username = get_stored_credentials()
+        .await?
+        .map(|c| c.username)
+        .unwrap_or_default();
+
+    let mut usernames = HashMap::new();
+    for resolved in folders::resolve_hosts(hosts)? {
+        let profile_credentials = if resolved.credential_profile.is_empty() {
+            None
+        } else {
+            read_credential(&profile_credential_target(&resolved.credential_profile))?
+        };
+        let username = match profile_credentials {
+            Some(credentials) => credentials.username,
+            None => default_username.clone(),
+        };
+        usernames.insert(resolved.host.hostname, username);
+    }
+    Ok(usernames)
+}
+
+// Hosts arranged by their group path, so exporters can write nested folders
+#[derive(Default)]
+struct GroupTree<'a> {
+    groups: BTreeMap<String, GroupTree<'a>>,
+    hosts: Vec<&'a Host>,
+}
+
+impl<'a> GroupTree<'a> {
+    fn build(hosts: &'a [Host]) -> Self {
+        let mut root = GroupTree::default();
+        for host in hosts {
+            let mut node = &mut root;
+            for segment in host.group.split('/').filter(|s| !s.trim().is_empty()) {
+                node = node.groups.entry(segment.trim().to_string()).or_default();
+            }
+            node.hosts.push(host);
+        }
+        root
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+fn to_rdcman(hosts: &[Host], usernames: &HashMap<String, String>) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\r\n");
+    xml.push_str("<RDCMan programVersion=\"2.7\" schemaVersion=\"3\">\r\n");
+    xml.push_str("  <file>\r\n");
+    xml.push_str("    <credentialsProfiles />\r\n");
+    xml.push_str("    <properties>\r\n");
+    xml.push_str("      <expanded>True</expanded>\r\n");
+    xml.push_str("      <name>ConnectX</name>\r\n");
+    xml.push_str("    </properties>\r\n");
+    write_rdcman_group(&mut xml, &GroupTree::build(hosts), usernames, 2);
+    xml.push_str("  </file>\r\n");
+    xml.push_str("  <connected />\r\n");
+    xml.push_str("  <favorites />\r\n");
+    xml.push_str("  <recentlyUsed />\r\n");
+    xml.push_str("</RDCMan>\r\n");
+    xml
+}
+
+fn write_rdcman_group(xml: &mut String, tree: &GroupTree, usernames: &HashMap<String, String>, depth: usize) {
+    let pad = indent(depth);
+
+    for (name, group) in &tree.groups {
+        xml.push_str(&format!("{}<group>\r\n", pad));
+        xml.push_str(&format!("{}  <properties>\r\n", pad));
+        xml.push_str(&format!("{}    <expanded>False</expanded>\r\n", pad));
+        xml.push_str(&format!("{}    <name>{}</name>\r\n", pad, xml_escape(name)));
+        xml.push_str(&format!("{}  </properties>\r\n", pad));
+        write_rdcman_group(xml, group, usernames, depth + 1);
+        xml.push_str(&format!("{}</group>\r\n", pad));
+    }
+
+    for host in &tree.hosts {
+        xml.push_str(&format!("{}<server>\r\n", pad));
+        xml.push_str(&format!("{}  <properties>\r\n", pad));
+        xml.push_str(&format!("{}    <name>{}</name>\r\n", pad, xml_escape(&host.hostname)));
+        if !host.description.is_empty() {
+            xml.push_str(&format!("{}    <comment>{}</comment>\r\n", pad, xml_escape(&host.description)));
+        }
+        xml.push_str(&format!("{}  </properties>\r\n", pad));
+
+        if let Some(port) = host.port {
+            xml.push_str(&format!("{}  <connectionSettings inherit=\"None\">\r\n", pad));
+            xml.push_str(&format!("{}    <connectToConsole>False</connectToConsole>\r\n", pad));
+            xml.push_str(&format!("{}    <startProgram />\r\n", pad));
+            xml.push_str(&format!("{}    <workingDir />\r\n", pad));
+            xml.push_str(&format!("{}    <port>{}</port>\r\n", pad, port));
+            xml.push_str(&format!("{}    <loadBalanceInfo />\r\n", pad));
+            xml.push_str(&format!("{}  </connectionSettings>\r\n", pad));
+        }
+
+        if !host.gateway.is_empty() {
+            xml.push_str(&format!("{}  <gatewaySettings inherit=\"None\">\r\n", pad));
+            xml.push_str(&format!("{}    <enabled>True</enabled>\r\n", pad));
+            xml.push_str(&format!("{}    <hostName>{}</hostName>\r\n", pad, xml_escape(&host.gateway)));
+            xml.push_str(&format!("{}    <logonMethod>Any</logonMethod>\r\n", pad));
+            xml.push_str(&format!("{}    <localBypass>False</localBypass>\r\n", pad));
+            xml.push_str(&format!("{}    <credSharing>True</credSharing>\r\n", pad));
+            xml.push_str(&format!("{}  </gatewaySettings>\r\n", pad));
+        }
+
+        if let Some(username) = usernames.get(&host.hostname).filter(|u| !u.is_empty()) {
+            let (domain, user) = split_username(username);
+            xml.push_str(&format!("{}  <logonCredentials inherit=\"None\">\r\n", pad));
+            xml.push_str(&format!("{}    <profileName scope=\"Local\">Custom</profileName>\r\n", pad));
+            xml.push_str(&format!("{}    <userName>{}</userName>\r\n", pad, xml_escape(user)));
+            xml.push_str(&format!("{}    <password />\r\n", pad));
+            xml.push_str(&format!("{}    <domain>{}</domain>\r\n", pad, xml_escape(domain)));
+            xml.push_str(&format!("{}  </logonCredentials>\r\n", pad));
+        }
+
+        xml.push_str(&format!("{}</server>\r\n", pad));
+    }
+}
+
+fn to_mremoteng(hosts: &[Host], usernames: &HashMap<String, String>) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\r\n");
+    xml.push_str("<mrng:Connections xmlns:mrng=\"http://mremoteng.org\" Name=\"Connections\" Export=\"false\" ConfVersion=\"2.6\">\r\n");
+    write_mremoteng_group(&mut xml, &GroupTree::build(hosts), usernames, "", 1);
+    xml.push_str("</mrng:Connections>\r\n");
+    xml
+}
+
+fn write_mremoteng_group(
+    xml: &mut String,
+    tree: &GroupTree,
+    usernames: &HashMap<String, String>,
+    path: &str,
+    depth: usize,
+) {
+    let pad = indent(depth);
+
+    for (name, group) in &tree.groups {
+        let group_path = format!("{}/{}", path, name);
+        xml.push_str(&format!(
+            "{}<Node Name=\"{}\" Type=\"Container\" Expanded=\"true\" Id=\"{}\">\r\n",
+            pad,
+            xml_escape(name),
+            stable_id(&group_path)
+        ));
+        write_mremoteng_group(xml, group, usernames, &group_path, depth + 1);
+        xml.push_str(&format!("{}</Node>\r\n", pad));
+    }
+
+    for host in &tree.hosts {
+        let username = usernames.get(&host.hostname).map(String::as_str).unwrap_or("");
+        let (domain, user) = split_username(username);
+        let gateway_usage = if host.gateway.is_empty() { "Never" } else { "Always" };
+        xml.push_str(&format!(
+            "{}<Node Name=\"{}\" Type=\"Connection\" Descr=\"{}\" Id=\"{}\" Hostname=\"{}\" Port=\"{}\" Protocol=\"RDP\" Username=\"{}\" Domain=\"{}\" RDGatewayUsageMethod=\"{}\" RDGatewayHostname=\"{}\" />\r\n",
+            pad,
+            xml_escape(&host.hostname),
+            xml_escape(&host.description),
+            stable_id(&format!("{}/{}", path, host.hostname)),
+            xml_escape(&host.hostname),
+            host.port.unwrap_or(3389),
+            xml_escape(user),
+            xml_escape(domain),
+            gateway_usage,
+            xml_escape(&host.gateway)
+        ));
+    }
+}
+
+// 64-bit FNV-1a. Unlike std's hashers its output is fixed, so ids survive
+// a Rust upgrade.
+fn fnv1a(bytes: &[u8], basis: u64) -> u64 {
+    bytes.iter().fold(basis, |hash, &b| (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+// mRemoteNG wants a GUID per node. Derive it from the node's path so exporting
+// the same inventory twice produces the same ids.
+fn stable_id(seed: &str) -> String {
+    let high = fnv1a(seed.as_bytes(), 0xcbf2_9ce4_8422_2325);
+    // Continue from the first half so the two halves differ
+    let low = fnv1a(seed.as_bytes(), high);
+
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+// DOMAIN\user is stored as one string, both managers keep the parts apart
+fn split_username(username: &str) -> (&str, &str) {
+    match username.split_once('\\') {
+        Some((domain, user)) => (domain, user),
+        None => ("", username),
+    }
+}
+
+fn write_rdp_bundle(path: &Path, hosts: &[Host], usernames: &HashMap<String, String>) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default();
+
+    // Folder defaults apply to the exported files just like when connecting
+    for resolved in folders::resolve_hosts(hosts)? {
+        let host = &resolved.host;
+        let username = usernames.get(&host.hostname).map(String::as_str).unwrap_or("");
+        let content = rdp::build_rdp_file(host, &resolved.rdp, username);
+
+        zip.start_file(archive_path(host), options)
+            .map_err(|e| format!("Failed to add {} to archive: {}", host.hostname, e))?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to add {} to archive: {}", host.hostname, e))?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(())
+}
+
+// Keep the folder structure inside the archive. Every segment is sanitized
+// so a group like "../../Startup" can't place files outside the extract folder.
+fn archive_path(host: &Host) -> String {
+    let mut segments: Vec<String> = host
+        .group
+        .split('/')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(sanitize_file_name)
+        .collect();
+    segments.push(format!("{}.rdp", sanitize_file_name(&host.hostname)));
+    segments.join("/")
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    // "." and ".." would refer to folders rather than name one
+    if name.chars().all(|c| c == '.') {
+        return "_".repeat(name.len());
+    }
+    name.chars()
+        .map(|c| if "<>:\"/\\|?*".contains(c) || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host;
+
+    #[test]
+    fn archive_paths_stay_inside_the_archive() {
+        let path = |group: &str| archive_path(&Host { group: group.to_string(), ..host("srv01") });
+        assert_eq!(path(""), "srv01.rdp");
+        assert_eq!(path("London/SQL"), "London/SQL/srv01.rdp");
+        assert_eq!(path("../../Startup"), "__/__/Startup/srv01.rdp");
+        assert_eq!(path("/London/./SQL/"), "London/_/SQL/srv01.rdp");
+        assert_eq!(path("C:\\Windows"), "C__Windows/srv01.rdp");
+    }
+
+    #[test]
+    fn rdcman_export_keeps_port_and_gateway() {
+        let hosts = vec![Host {
+            group: "London".to_string(),
+            port: Some(3390),
+            gateway: "rdgw.example.com".to_string(),
+            ..host("srv01")
+        }];
+        let usernames = HashMap::from([("srv01".to_string(), "CORP\\admin".to_string())]);
+
+        let xml = to_rdcman(&hosts, &usernames);
+        assert!(xml.contains("<port>3390</port>"));
+        assert!(xml.contains("<hostName>rdgw.example.com</hostName>"));
+        assert!(xml.contains("<userName>admin</userName>"));
+        assert!(xml.contains("<domain>CORP</domain>"));
+    }
+
+    #[test]
+    fn mremoteng_ids_are_stable() {
+        let hosts = vec![Host { group: "London".to_string(), ..host("srv01") }];
+        assert_eq!(to_mremoteng(&hosts, &HashMap::new()), to_mremoteng(&hosts, &HashMap::new()));
+        assert_ne!(stable_id("London"), stable_id("London/srv01"));
+    }
+}
@@ -0,0 +1,344 @@
+use crate::rdp::RdpSettings;
+use crate::{get_hosts, log_to_file, storage, validation, write_hosts, Host};
+use std::collections::BTreeMap;
+
+const FOLDERS_FILE: &str = "folders.json";
+
+// Settings a folder passes down to every host (and folder) inside it
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FolderDefaults {
+    // Name of a saved credential profile, empty to use the default credentials
+    pub credential_profile: String,
+    pub gateway: String,
+    pub port: Option<u16>,
+    pub rdp: RdpSettings,
+}
+
+// Folders are keyed by their full path ("London/SQL"). Hosts point at their
+// folder through Host::group, so a folder only needs an entry here when it is
+// empty or has defaults.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct FolderStore {
+    folders: BTreeMap<String, FolderDefaults>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FolderNode {
+    name: String,
+    path: String,
+    defaults: FolderDefaults,
+    hosts: Vec<Host>,
+    children: Vec<FolderNode>,
+}
+
+// Connection settings for a host after applying its folders' defaults
+pub struct ResolvedHost {
+    pub host: Host,
+    pub rdp: RdpSettings,
+    pub credential_profile: String,
+}
+
+fn load_store() -> Result<FolderStore, String> {
+    storage::load_json(FOLDERS_FILE)
+}
+
+fn save_store(store: &FolderStore) -> Result<(), String> {
+    storage::save_json(FOLDERS_FILE, store)
+}
+
+// "/London//SQL " -> "London/SQL"
+pub fn normalize_path(path: &str) -> String {
+    path.split('/')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn parent_path(path: &str) -> &str {
+    path.rsplit_once('/').map(|(parent, _)| parent).unwrap_or("")
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+fn is_within(path: &str, folder: &str) -> bool {
+    path == folder || path.starts_with(&format!("{}/", folder))
+}
+
+// "London/SQL/Cluster" -> ["London", "London/SQL", "London/SQL/Cluster"]
+fn ancestors(path: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        current = join_path(&current, segment);
+        result.push(current.clone());
+    }
+    result
+}
+
+pub fn resolve_host(host: &Host) -> Result<ResolvedHost, String> {
+    Ok(resolve_with(&load_store()?, host))
+}
+
+pub fn resolve_hosts(hosts: &[Host]) -> Result<Vec<ResolvedHost>, String> {
+    let store = load_store()?;
+    Ok(hosts.iter().map(|host| resolve_with(&store, host)).collect())
+}
+
+fn resolve_with(store: &FolderStore, host: &Host) -> ResolvedHost {
+    // Walk from the top folder down so deeper folders override their parents
+    let mut defaults = FolderDefaults::default();
+    for path in ancestors(&normalize_path(&host.group)) {
+        if let Some(folder) = store.folders.get(&path) {
+            if !folder.credential_profile.is_empty() {
+                defaults.credential_profile = folder.credential_profile.clone();
+            }
+            if !folder.gateway.is_empty() {
+                defaults.gateway = folder.gateway.clone();
+            }
+            defaults.port = folder.port.or(defaults.port);
+            defaults.rdp = defaults.rdp.overlay(&folder.rdp);
+        }
+    }
+
+    // Anything set on the host itself wins over its folders
+    let mut resolved = host.clone();
+    if resolved.gateway.is_empty() {
+        resolved.gateway = defaults.gateway;
+    }
+    resolved.port = resolved.port.or(defaults.port);
+
+    ResolvedHost {
+        rdp: defaults.rdp.overlay(&host.rdp),
+        host: resolved,
+        credential_profile: defaults.credential_profile,
+    }
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    children: BTreeMap<String, TreeBuilder>,
+    hosts: Vec<Host>,
+}
+
+impl TreeBuilder {
+    fn folder_mut(&mut self, path: &str) -> &mut TreeBuilder {
+        let mut node = self;
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            node = node.children.entry(segment.to_string()).or_default();
+        }
+        node
+    }
+
+    fn into_node(self, name: String, path: String, store: &FolderStore) -> FolderNode {
+        let children = self
+            .children
+            .into_iter()
+            .map(|(child_name, child)| {
+                let child_path = join_path(&path, &child_name);
+                child.into_node(child_name, child_path, store)
+            })
+            .collect();
+
+        FolderNode {
+            defaults: store.folders.get(&path).cloned().unwrap_or_default(),
+            name,
+            path,
+            hosts: self.hosts,
+            children,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_folder_tree() -> Result<FolderNode, String> {
+    let store = load_store()?;
+    let mut root = TreeBuilder::default();
+
+    for path in store.folders.keys() {
+        root.folder_mut(path);
+    }
+    for host in get_hosts()? {
+        let path = normalize_path(&host.group);
+        root.folder_mut(&path).hosts.push(host);
+    }
+
+    Ok(root.into_node(String::new(), String::new(), &store))
+}
+
+#[tauri::command]
+pub fn create_folder(path: String) -> Result<(), String> {
+    let path = normalize_path(&path);
+    if path.is_empty() {
+        return Err("Folder name cannot be empty".to_string());
+    }
+
+    let mut store = load_store()?;
+    if store.folders.contains_key(&path) {
+        return Err(format!("Folder already exists: {}", path));
+    }
+    store.folders.insert(path, FolderDefaults::default());
+    save_store(&store)
+}
+
+#[tauri::command]
+pub fn rename_folder(path: String, new_name: String) -> Result<(), String> {
+    let path = normalize_path(&path);
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains('/') {
+        return Err("Folder names cannot be empty or contain '/'".to_string());
+    }
+
+    let new_path = join_path(parent_path(&path), new_name);
+    relocate_folder(&path, &new_path)
+}
+
+#[tauri::command]
+pub fn move_folder(path: String, new_parent: String) -> Result<(), String> {
+    let path = normalize_path(&path);
+    let new_parent = normalize_path(&new_parent);
+    if is_within(&new_parent, &path) {
+        return Err("Cannot move a folder into itself".to_string());
+    }
+
+    let name = path.rsplit('/').next().unwrap_or("");
+    let new_path = join_path(&new_parent, name);
+    relocate_folder(&path, &new_path)
+}
+
+// Move a folder and everything below it, both the stored folder settings and
+// the group of every host inside it
+fn relocate_folder(old_path: &str, new_path: &str) -> Result<(), String> {
+    if old_path.is_empty() {
+        return Err("The root folder cannot be moved or renamed".to_string());
+    }
+    if old_path == new_path {
+        return Ok(());
+    }
+
+    let mut store = load_store()?;
+    let mut hosts = get_hosts()?;
+
+    let folder_exists = store.folders.keys().any(|p| is_within(p, old_path))
+        || hosts.iter().any(|h| is_within(&normalize_path(&h.group), old_path));
+    if !folder_exists {
+        return Err(format!("Folder not found: {}", old_path));
+    }
+    let target_exists = store.folders.keys().any(|p| is_within(p, new_path))
+        || hosts.iter().any(|h| is_within(&normalize_path(&h.group), new_path));
+    if target_exists {
+        return Err(format!("Folder already exists: {}", new_path));
+    }
+
+    let rebase = |path: &str| format!("{}{}", new_path, &path[old_path.len()..]);
+
+    store.folders = std::mem::take(&mut store.folders)
+        .into_iter()
+        .map(|(path, defaults)| {
+            if is_within(&path, old_path) {
+                (rebase(&path), defaults)
+            } else {
+                (path, defaults)
+            }
+        })
+        .collect();
+
+    let mut hosts_changed = false;
+    for host in hosts.iter_mut() {
+        let group = normalize_path(&host.group);
+        if is_within(&group, old_path) {
+            host.group = rebase(&group);
+            hosts_changed = true;
+        }
+    }
+
+    if hosts_changed {
+        write_hosts(&hosts)?;
+    }
+    save_store(&store)?;
+
+    log_to_file(&format!("Moved folder {} to {}", old_path, new_path));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn move_host(hostname: String, folder: String) -> Result<(), String> {
+    let folder = normalize_path(&folder);
+    let mut hosts = get_hosts()?;
+
+    let host = hosts
+        .iter_mut()
+        .find(|h| h.hostname.eq_ignore_ascii_case(&hostname))
+        .ok_or(format!("Host not found: {}", hostname))?;
+    host.group = folder;
+
+    write_hosts(&hosts)
+}
+
+#[tauri::command]
+pub fn set_folder_defaults(path: String, defaults: FolderDefaults) -> Result<(), String> {
+    let path = normalize_path(&path);
+    if path.is_empty() {
+        return Err("Folder name cannot be empty".to_string());
+    }
+
+    let defaults = normalize_defaults(&defaults)?;
+    let mut store = load_store()?;
+    store.folders.insert(path, defaults);
+    save_store(&store)
+}
+
+// Hold folder defaults to the same rules as the host fields they fill in. The
+// gateway ends up in the .rdp file, so it must not carry anything but a name.
+fn normalize_defaults(defaults: &FolderDefaults) -> Result<FolderDefaults, String> {
+    let mut normalized = defaults.clone();
+    normalized.credential_profile = defaults.credential_profile.trim().to_string();
+
+    if defaults.port == Some(0) {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+
+    normalized.gateway = if defaults.gateway.trim().is_empty() {
+        String::new()
+    } else {
+        let (name, port) = validation::parse_address(&defaults.gateway)
+            .map_err(|e| format!("Invalid gateway: {}", e))?;
+        let name = validation::validate_hostname(&name)
+            .map_err(|e| format!("Invalid gateway: {}", e))?;
+        validation::format_address(&name, port)
+    };
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_gateway(gateway: &str) -> FolderDefaults {
+        FolderDefaults { gateway: gateway.to_string(), ..FolderDefaults::default() }
+    }
+
+    #[test]
+    fn rejects_gateways_that_are_not_addresses() {
+        assert!(normalize_defaults(&with_gateway("rdgw.example.com\r\nusername:s:admin")).is_err());
+        assert!(normalize_defaults(&with_gateway("rdgw example.com")).is_err());
+        assert!(normalize_defaults(&with_gateway("rdgw.example.com:0")).is_err());
+        assert!(normalize_defaults(&FolderDefaults { port: Some(0), ..FolderDefaults::default() }).is_err());
+    }
+
+    #[test]
+    fn normalizes_gateways() {
+        let normalized = normalize_defaults(&with_gateway(" rdgw.example.com:4443 ")).unwrap();
+        assert_eq!(normalized.gateway, "rdgw.example.com:4443");
+        assert_eq!(normalize_defaults(&with_gateway("  ")).unwrap().gateway, "");
+    }
+}
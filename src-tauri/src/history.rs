@@ -0,0 +1,317 @@
+use crate::{get_hosts, log_to_file, storage, trash, validation, write_hosts, Host};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "history.json";
+
+// Oldest entries are dropped beyond this
+const MAX_ENTRIES: usize = 5000;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FieldChange {
+    field: String,
+    // Null when the host was created (old) or deleted (new)
+    old: Value,
+    new: Value,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    id: u64,
+    // Name of the host after the change, or before it for deletions
+    hostname: String,
+    // Seconds since the epoch
+    timestamp: u64,
+    user: String,
+    // "created", "updated", "deleted", "imported", "reverted", ...
+    action: String,
+    changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct HistoryStore {
+    entries: Vec<HistoryEntry>,
+}
+
+fn current_user() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn host_fields(host: Option<&Host>) -> serde_json::Map<String, Value> {
+    match host.map(serde_json::to_value) {
+        Some(Ok(Value::Object(fields))) => fields,
+        _ => serde_json::Map::new(),
+    }
+}
+
+// Field-level differences between two versions of a host. Either side can be
+// missing, for hosts that were just created or deleted.
+fn diff_fields(old: Option<&Host>, new: Option<&Host>) -> Vec<FieldChange> {
+    let old_fields = host_fields(old);
+    let new_fields = host_fields(new);
+
+    let mut names: Vec<&String> = old_fields.keys().chain(new_fields.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let old = old_fields.get(name).cloned().unwrap_or(Value::Null);
+            let new = new_fields.get(name).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| FieldChange { field: name.clone(), old, new })
+        })
+        .collect()
+}
+
+fn append(entries: Vec<HistoryEntry>) -> Result<(), String> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut store: HistoryStore = storage::load_json(HISTORY_FILE)?;
+    // Ids come from the clock, two saves within the same millisecond would
+    // otherwise share one
+    let mut next_id = store.entries.last().map_or(0, |e| e.id + 1);
+    for mut entry in entries {
+        entry.id = entry.id.max(next_id);
+        next_id = entry.id + 1;
+        store.entries.push(entry);
+    }
+    if store.entries.len() > MAX_ENTRIES {
+        let excess = store.entries.len() - MAX_ENTRIES;
+        store.entries.drain(..excess);
+    }
+    storage::save_json(HISTORY_FILE, &store)
+}
+
+fn new_entries<'a>(action: &str, pairs: impl IntoIterator<Item = (Option<&'a Host>, Option<&'a Host>)>) -> Vec<HistoryEntry> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let user = current_user();
+
+    pairs
+        .into_iter()
+        .filter_map(|(old, new)| {
+            let changes = diff_fields(old, new);
+            let hostname = new.or(old)?.hostname.clone();
+            (!changes.is_empty()).then_some((hostname, changes))
+        })
+        .enumerate()
+        .map(|(i, (hostname, changes))| HistoryEntry {
+            // Entries written together share a timestamp, so number them
+            id: millis * 1000 + i as u64,
+            hostname,
+            timestamp: millis / 1000,
+            user: user.clone(),
+            action: action.to_string(),
+            changes,
+        })
+        .collect()
+}
+
+// Record a change to a single host. History is a record of what happened, not
+// part of it, so failing to write it is logged rather than failing the change.
+pub fn record(action: &str, old: Option<&Host>, new: Option<&Host>) {
+    if let Err(e) = append(new_entries(action, [(old, new)])) {
+        log_to_file(&format!("Failed to record host history: {}", e));
+    }
+}
+
+// Record every host that differs between two versions of the inventory,
+// matching hosts by name
+pub fn record_all(action: &str, before: &[Host], after: &[Host]) {
+    let before_by_name: HashMap<String, &Host> = before.iter().map(|h| (h.hostname.to_lowercase(), h)).collect();
+    let after_by_name: HashMap<String, &Host> = after.iter().map(|h| (h.hostname.to_lowercase(), h)).collect();
+
+    let pairs = after
+        .iter()
+        .map(|h| (before_by_name.get(&h.hostname.to_lowercase()).copied(), Some(h)))
+        .chain(
+            before
+                .iter()
+                .filter(|h| !after_by_name.contains_key(&h.hostname.to_lowercase()))
+                .map(|h| (Some(h), None)),
+        );
+
+    if let Err(e) = append(new_entries(action, pairs)) {
+        log_to_file(&format!("Failed to record host history: {}", e));
+    }
+}
+
+fn renamed_from(entry: &HistoryEntry) -> Option<String> {
+    entry
+        .changes
+        .iter()
+        .find(|c| c.field == "hostname" && !c.old.is_null() && !c.new.is_null())
+        .and_then(|c| c.old.as_str().map(str::to_string))
+}
+
+// Changes to a host, newest first, following it back through renames
+#[tauri::command]
+pub fn get_host_history(hostname: String) -> Result<Vec<HistoryEntry>, String> {
+    let store: HistoryStore = storage::load_json(HISTORY_FILE)?;
+
+    let mut names = vec![hostname.to_lowercase()];
+    let mut history = Vec::new();
+    for entry in store.entries.into_iter().rev() {
+        if !names.contains(&entry.hostname.to_lowercase()) {
+            continue;
+        }
+        if let Some(old_name) = renamed_from(&entry) {
+            names.push(old_name.to_lowercase());
+        }
+        history.push(entry);
+    }
+    Ok(history)
+}
+
+// Undo a single recorded change by putting the old values back. Refused when
+// the host has been changed again since, so a revert never silently drops a
+// later edit.
+#[tauri::command]
+pub fn revert_change(id: u64) -> Result<(), String> {
+    let store: HistoryStore = storage::load_json(HISTORY_FILE)?;
+    let entry = store
+        .entries
+        .iter()
+        .find(|e| e.id == id)
+        .ok_or(format!("History entry not found: {}", id))?;
+
+    let mut hosts = get_hosts()?;
+    let (old, new) = apply_revert(entry, &mut hosts)?;
+
+    // Undoing a creation deletes the host again
+    if let (Some(removed), None) = (&old, &new) {
+        trash::move_to_trash(std::slice::from_ref(removed))?;
+    }
+    write_hosts(&hosts)?;
+    // Undoing a deletion brings the host back, so it no longer belongs in the trash
+    if let (None, Some(restored)) = (&old, &new) {
+        if let Err(e) = trash::take_back(&restored.hostname) {
+            log_to_file(&format!("Failed to remove {} from the deleted hosts: {}", restored.hostname, e));
+        }
+    }
+    record("reverted", old.as_ref(), new.as_ref());
+    log_to_file(&format!("Reverted change {} to {}", id, entry.hostname));
+    Ok(())
+}
+
+// Revert the entry in `hosts` and return the host before and after
+fn apply_revert(entry: &HistoryEntry, hosts: &mut Vec<Host>) -> Result<(Option<Host>, Option<Host>), String> {
+    let index = hosts.iter().position(|h| h.hostname.eq_ignore_ascii_case(&entry.hostname));
+    let current_fields = host_fields(index.map(|i| &hosts[i]));
+
+    let still_current = entry
+        .changes
+        .iter()
+        .all(|c| current_fields.get(&c.field).cloned().unwrap_or(Value::Null) == c.new);
+    if !still_current {
+        return Err(format!("{} has changed since, revert the later changes first", entry.hostname));
+    }
+
+    // Hostname is always set, so it only goes from or to null when the host
+    // itself was created or deleted
+    let hostname_change = entry.changes.iter().find(|c| c.field == "hostname");
+    let created = hostname_change.is_some_and(|c| c.old.is_null());
+    let deleted = hostname_change.is_some_and(|c| c.new.is_null());
+
+    match index {
+        Some(i) if created => Ok((Some(hosts.remove(i)), None)),
+        Some(i) => {
+            // Fields left out when serializing (no tags, no RDP settings) show
+            // up as null and have to be left out again
+            let mut fields = current_fields;
+            for change in &entry.changes {
+                if change.old.is_null() {
+                    fields.remove(&change.field);
+                } else {
+                    fields.insert(change.field.clone(), change.old.clone());
+                }
+            }
+            let reverted: Host = serde_json::from_value(Value::Object(fields))
+                .map_err(|e| format!("Failed to revert {}: {}", entry.hostname, e))?;
+            let others = || hosts.iter().enumerate().filter(move |(j, _)| *j != i).map(|(_, h)| h);
+            if others().any(|h| h.hostname.eq_ignore_ascii_case(&reverted.hostname)) {
+                return Err(format!("{} already exists in the inventory", reverted.hostname));
+            }
+            if let Some(conflict) = validation::name_conflict(&reverted, others()) {
+                return Err(conflict);
+            }
+            let old = std::mem::replace(&mut hosts[i], reverted.clone());
+            Ok((Some(old), Some(reverted)))
+        }
+        None if deleted => {
+            let fields = entry
+                .changes
+                .iter()
+                .filter(|c| !c.old.is_null())
+                .map(|c| (c.field.clone(), c.old.clone()))
+                .collect();
+            let restored: Host = serde_json::from_value(Value::Object(fields))
+                .map_err(|e| format!("Failed to restore {}: {}", entry.hostname, e))?;
+            // Its name may have become another host's alias since
+            if let Some(conflict) = validation::name_conflict(&restored, hosts.iter()) {
+                return Err(conflict);
+            }
+            hosts.push(restored.clone());
+            Ok((None, Some(restored)))
+        }
+        None => Err(format!("Host not found: {}", entry.hostname)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{full_host, host, strings};
+
+    fn entry(action: &str, old: Option<&Host>, new: Option<&Host>) -> HistoryEntry {
+        new_entries(action, [(old, new)]).remove(0)
+    }
+
+    #[test]
+    fn reverting_a_deletion_restores_every_field() {
+        let deleted = full_host();
+        let mut hosts = vec![host("srv01")];
+
+        let (old, new) = apply_revert(&entry("deleted", Some(&deleted), None), &mut hosts).unwrap();
+        assert_eq!(old, None);
+        assert_eq!(new.as_ref(), Some(&deleted));
+        assert_eq!(hosts, vec![host("srv01"), deleted]);
+    }
+
+    #[test]
+    fn reverting_a_deletion_checks_names() {
+        let deleted = Host { aliases: strings(&["files"]), ..host("srv01") };
+        let mut hosts = vec![Host { aliases: strings(&["FILES"]), ..host("srv02") }];
+        assert!(apply_revert(&entry("deleted", Some(&deleted), None), &mut hosts).is_err());
+
+        let mut hosts = vec![Host { aliases: strings(&["srv01"]), ..host("srv02") }];
+        assert!(apply_revert(&entry("deleted", Some(&host("srv01")), None), &mut hosts).is_err());
+        assert_eq!(hosts.len(), 1);
+    }
+
+    #[test]
+    fn reverting_an_update_puts_the_old_values_back() {
+        let before = host("srv01");
+        let after = Host { description: "File server".to_string(), tags: strings(&["prod"]), ..host("srv01") };
+        let mut hosts = vec![after.clone()];
+
+        apply_revert(&entry("updated", Some(&before), Some(&after)), &mut hosts).unwrap();
+        assert_eq!(hosts, vec![before]);
+    }
+
+    #[test]
+    fn refuses_to_revert_over_a_later_change() {
+        let before = host("srv01");
+        let after = Host { port: Some(3390), ..host("srv01") };
+        let mut hosts = vec![Host { port: Some(3391), ..host("srv01") }];
+        assert!(apply_revert(&entry("updated", Some(&before), Some(&after)), &mut hosts).is_err());
+    }
+}
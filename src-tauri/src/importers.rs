@@ -0,0 +1,466 @@
+use crate::rdp::RdpSettings;
+use crate::staging::{self, StageMode, StagedChanges};
+use crate::{get_hosts, validation, Host};
+use roxmltree::{Document, Node};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+const DEFAULT_RDP_PORT: u16 = 3389;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportFormat {
+    MRemoteNg,
+    RdcMan,
+    RoyalTs,
+}
+
+impl ImportFormat {
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "mremoteng" => Ok(ImportFormat::MRemoteNg),
+            "rdcman" | "rdg" => Ok(ImportFormat::RdcMan),
+            "royalts" | "royal" => Ok(ImportFormat::RoyalTs),
+            other => Err(format!("Unknown import format: {}", other)),
+        }
+    }
+
+    fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "xml" => Ok(ImportFormat::MRemoteNg),
+            "rdg" => Ok(ImportFormat::RdcMan),
+            "json" => Ok(ImportFormat::RoyalTs),
+            _ => Err(format!("Cannot tell the import format from {}", path.display())),
+        }
+    }
+}
+
+// Import hosts from another connection manager. Nothing is written yet, the
+// changes are staged for review and applied with apply_staged_changes.
+#[tauri::command]
+pub fn import_hosts(path: String, format: Option<String>) -> Result<StagedChanges, String> {
+    let path = Path::new(&path);
+    let format = match format {
+        Some(name) => ImportFormat::from_name(&name)?,
+        None => ImportFormat::from_path(path)?,
+    };
+
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let contents = String::from_utf8_lossy(&bytes);
+    // Both XML formats are commonly saved with a BOM
+    let contents = contents.trim_start_matches('\u{feff}');
+
+    let mut skipped = Vec::new();
+    let parsed = match format {
+        ImportFormat::MRemoteNg => parse_mremoteng(contents, &mut skipped)?,
+        ImportFormat::RdcMan => parse_rdcman(contents, &mut skipped)?,
+        ImportFormat::RoyalTs => parse_royalts(contents, &mut skipped)?,
+    };
+
+    merge_import(path, parsed, skipped)
+}
+
+// Validate imported hosts and stage them for merging into the inventory,
+// shared by every import format. `skipped` carries entries the parser
+// already had to drop.
+pub fn merge_import(path: &Path, parsed: Vec<Host>, mut skipped: Vec<String>) -> Result<StagedChanges, String> {
+    let local = get_hosts()?;
+
+    // The same server can appear in several folders, keep the first one
+    let mut seen = HashSet::new();
+    let mut imported = Vec::new();
+    for host in parsed {
+        let mut host = match validation::normalize_host(&host) {
+            Ok(host) => host,
+            Err(errors) => {
+                skipped.push(format!("{}: {}", host.hostname, validation::describe(&errors)));
+                continue;
+            }
+        };
+        if !seen.insert(host.hostname.to_lowercase()) {
+            skipped.push(format!("{}: duplicate entry", host.hostname));
+            continue;
+        }
+
+        // Keep what the source file has no way to say: per-host RDP
+        // settings and favorites, and the OS, aliases, tags and custom fields
+        // unless the import brings its own
+        if let Some(existing) = local.iter().find(|h| h.hostname.eq_ignore_ascii_case(&host.hostname)) {
+            if host.os.is_empty() {
+                host.os = existing.os.clone();
+            }
+            if host.aliases.is_empty() {
+                host.aliases = existing.aliases.clone();
+            }
+            if host.tags.is_empty() {
+                host.tags = existing.tags.clone();
+            }
+            if host.fields.is_empty() {
+                host.fields = existing.fields.clone();
+            }
+            host.favorite |= existing.favorite;
+            host.rdp = existing.rdp.clone();
+        }
+        imported.push(host);
+    }
+
+    staging::stage(&path.display().to_string(), StageMode::Merge, imported, skipped)
+}
+
+fn new_host(hostname: &str, description: &str, group: &[String], port: Option<u16>, gateway: &str) -> Host {
+    Host {
+        hostname: hostname.trim().to_string(),
+        description: description.trim().to_string(),
+        group: group.join("/"),
+        port: port.filter(|p| *p != DEFAULT_RDP_PORT),
+        gateway: gateway.trim().to_string(),
+        aliases: Vec::new(),
+        os: String::new(),
+        tags: Vec::new(),
+        fields: BTreeMap::new(),
+        favorite: false,
+        rdp: RdpSettings::default(),
+    }
+}
+
+// mRemoteNG confCons.xml: nested <Node> elements, Type="Container" for folders
+fn parse_mremoteng(contents: &str, skipped: &mut Vec<String>) -> Result<Vec<Host>, String> {
+    let doc = Document::parse(contents)
+        .map_err(|e| format!("Failed to parse mRemoteNG file: {}", e))?;
+    let root = doc.root_element();
+
+    if root.attribute("FullFileEncryption") == Some("true") {
+        return Err("mRemoteNG files with full file encryption are not supported, export without it".to_string());
+    }
+
+    let mut hosts = Vec::new();
+    let mut path = Vec::new();
+    collect_mremoteng(root, &mut path, &mut hosts, skipped);
+    Ok(hosts)
+}
+
+fn collect_mremoteng(node: Node, path: &mut Vec<String>, hosts: &mut Vec<Host>, skipped: &mut Vec<String>) {
+    for child in node.children().filter(|n| n.has_tag_name("Node")) {
+        let name = child.attribute("Name").unwrap_or("");
+        match child.attribute("Type") {
+            Some("Container") => {
+                path.push(name.to_string());
+                collect_mremoteng(child, path, hosts, skipped);
+                path.pop();
+            }
+            _ => {
+                let protocol = child.attribute("Protocol").unwrap_or("RDP");
+                if protocol != "RDP" {
+                    skipped.push(format!("{}: {} connections are not supported", name, protocol));
+                    continue;
+                }
+                let hostname = child.attribute("Hostname").unwrap_or("");
+                if hostname.trim().is_empty() {
+                    skipped.push(format!("{}: no hostname", name));
+                    continue;
+                }
+                let port = child.attribute("Port").and_then(|p| p.trim().parse().ok());
+                // RDGatewayUsageMethod is Never, Always or Detect
+                let gateway = match child.attribute("RDGatewayUsageMethod") {
+                    Some("Never") => "",
+                    _ => child.attribute("RDGatewayHostname").unwrap_or(""),
+                };
+                let description = child.attribute("Descr").unwrap_or("");
+                hosts.push(new_host(hostname, description, path, port, gateway));
+            }
+        }
+    }
+}
+
+// Remote Desktop Connection Manager .rdg: <group> and <server> elements, each
+// with a <properties> block; connection and gateway settings can be set on a
+// group and inherited by everything below it
+fn parse_rdcman(contents: &str, skipped: &mut Vec<String>) -> Result<Vec<Host>, String> {
+    let doc = Document::parse(contents)
+        .map_err(|e| format!("Failed to parse RDCMan file: {}", e))?;
+    let file = doc
+        .root_element()
+        .children()
+        .find(|n| n.has_tag_name("file"))
+        .ok_or("Not an RDCMan file: missing <file> element".to_string())?;
+
+    let mut hosts = Vec::new();
+    let mut path = Vec::new();
+    let inherited = RdcManSettings {
+        port: rdcman_port(file),
+        gateway: rdcman_gateway(file).unwrap_or_default(),
+    };
+    collect_rdcman(file, &mut path, &inherited, &mut hosts, skipped);
+    Ok(hosts)
+}
+
+#[derive(Clone, Default)]
+struct RdcManSettings {
+    port: Option<u16>,
+    gateway: String,
+}
+
+fn collect_rdcman(
+    node: Node,
+    path: &mut Vec<String>,
+    inherited: &RdcManSettings,
+    hosts: &mut Vec<Host>,
+    skipped: &mut Vec<String>,
+) {
+    for child in node.children().filter(|n| n.is_element()) {
+        let settings = RdcManSettings {
+            port: rdcman_port(child).or(inherited.port),
+            gateway: rdcman_gateway(child).unwrap_or_else(|| inherited.gateway.clone()),
+        };
+
+        match child.tag_name().name() {
+            "group" => {
+                path.push(rdcman_property(child, "name").to_string());
+                collect_rdcman(child, path, &settings, hosts, skipped);
+                path.pop();
+            }
+            "server" => {
+                let hostname = rdcman_property(child, "name");
+                if hostname.trim().is_empty() {
+                    skipped.push(format!("{}: no hostname", rdcman_property(child, "displayName")));
+                    continue;
+                }
+                let description = rdcman_property(child, "comment");
+                hosts.push(new_host(hostname, description, path, settings.port, &settings.gateway));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn rdcman_property<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    node.children()
+        .find(|n| n.has_tag_name("properties"))
+        .and_then(|props| props.children().find(|n| n.has_tag_name(name)))
+        .and_then(|n| n.text())
+        .unwrap_or("")
+}
+
+// Settings blocks marked inherit="FromParent" defer to the enclosing group
+fn rdcman_settings<'a, 'input>(node: Node<'a, 'input>, block: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.has_tag_name(block))
+        .filter(|n| n.attribute("inherit") != Some("FromParent"))
+}
+
+fn rdcman_port(node: Node) -> Option<u16> {
+    rdcman_settings(node, "connectionSettings")?
+        .children()
+        .find(|n| n.has_tag_name("port"))?
+        .text()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn rdcman_gateway(node: Node) -> Option<String> {
+    let settings = rdcman_settings(node, "gatewaySettings")?;
+    let value = |name: &str| {
+        settings
+            .children()
+            .find(|n| n.has_tag_name(name))
+            .and_then(|n| n.text())
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    };
+    if value("enabled").eq_ignore_ascii_case("true") {
+        Some(value("hostName"))
+    } else {
+        Some(String::new())
+    }
+}
+
+// Royal TS JSON: nested "Objects" arrays, folders have Type "Folder" and
+// connections carry ComputerName. A "Path" property places an object in a
+// folder without nesting it.
+fn parse_royalts(contents: &str, skipped: &mut Vec<String>) -> Result<Vec<Host>, String> {
+    let value: serde_json::Value = serde_json::from_str(contents)
+        .map_err(|e| format!("Failed to parse Royal TS file: {}", e))?;
+
+    let objects = match &value {
+        serde_json::Value::Array(_) => &value,
+        _ => value
+            .get("Objects")
+            .ok_or("Not a Royal TS export: missing \"Objects\"".to_string())?,
+    };
+
+    let mut hosts = Vec::new();
+    collect_royalts(objects, &[], &mut hosts, skipped);
+    Ok(hosts)
+}
+
+fn collect_royalts(
+    objects: &serde_json::Value,
+    path: &[String],
+    hosts: &mut Vec<Host>,
+    skipped: &mut Vec<String>,
+) {
+    let Some(objects) = objects.as_array() else {
+        return;
+    };
+
+    for object in objects {
+        let text = |key: &str| object.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        let name = text("Name");
+        let object_type = text("Type");
+
+        // Path is relative to the enclosing folder and uses / or \ as separator
+        let mut object_path = path.to_vec();
+        object_path.extend(
+            text("Path")
+                .split(['/', '\\'])
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| s.trim().to_string()),
+        );
+
+        if object_type == "Folder" {
+            object_path.push(name.to_string());
+            if let Some(children) = object.get("Objects") {
+                collect_royalts(children, &object_path, hosts, skipped);
+            }
+            continue;
+        }
+
+        if !object_type.is_empty() && object_type != "RemoteDesktopConnection" {
+            skipped.push(format!("{}: {} objects are not supported", name, object_type));
+            continue;
+        }
+
+        let hostname = text("ComputerName");
+        if hostname.trim().is_empty() {
+            skipped.push(format!("{}: no computer name", name));
+            continue;
+        }
+
+        let port = object
+            .get("Port")
+            .and_then(|p| p.as_u64().or_else(|| p.as_str().and_then(|s| s.trim().parse().ok())))
+            .and_then(|p| u16::try_from(p).ok());
+        let gateway = ["GatewayHostName", "RDGatewayHostName", "SecureGatewayHost"]
+            .iter()
+            .map(|key| text(key))
+            .find(|v| !v.is_empty())
+            .unwrap_or("");
+        hosts.push(new_host(hostname, text("Description"), &object_path, port, gateway));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host;
+
+    #[test]
+    fn reads_mremoteng_folders_and_connections() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<mrng:Connections xmlns:mrng="http://mremoteng.org" Name="Connections">
+  <Node Name="London" Type="Container">
+    <Node Name="SQL" Type="Container">
+      <Node Name="sql" Type="Connection" Descr="SQL node" Hostname="lon-sql01" Port="3390" Protocol="RDP"
+            RDGatewayUsageMethod="Always" RDGatewayHostname="rdgw.example.com" />
+    </Node>
+    <Node Name="dc" Type="Connection" Hostname="lon-dc01" Port="3389" Protocol="RDP"
+          RDGatewayUsageMethod="Never" RDGatewayHostname="rdgw.example.com" />
+    <Node Name="switch" Type="Connection" Hostname="lon-sw01" Protocol="SSH2" />
+  </Node>
+  <Node Name="empty" Type="Connection" Hostname="" Protocol="RDP" />
+</mrng:Connections>"#;
+
+        let mut skipped = Vec::new();
+        let hosts = parse_mremoteng(xml, &mut skipped).unwrap();
+        assert_eq!(
+            hosts,
+            vec![
+                Host {
+                    description: "SQL node".to_string(),
+                    group: "London/SQL".to_string(),
+                    port: Some(3390),
+                    gateway: "rdgw.example.com".to_string(),
+                    ..host("lon-sql01")
+                },
+                Host { group: "London".to_string(), ..host("lon-dc01") },
+            ]
+        );
+        assert_eq!(skipped.len(), 2);
+        assert!(parse_mremoteng(r#"<Connections FullFileEncryption="true" />"#, &mut skipped).is_err());
+    }
+
+    #[test]
+    fn reads_rdcman_groups_with_inherited_settings() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<RDCMan programVersion="2.7" schemaVersion="3">
+  <file>
+    <properties><name>Servers</name></properties>
+    <group>
+      <properties><name>London</name></properties>
+      <connectionSettings inherit="None"><port>3390</port></connectionSettings>
+      <gatewaySettings inherit="None"><enabled>True</enabled><hostName>rdgw.example.com</hostName></gatewaySettings>
+      <server>
+        <properties><name>lon-sql01</name><comment>SQL node</comment></properties>
+        <connectionSettings inherit="FromParent"><port>4000</port></connectionSettings>
+      </server>
+      <server>
+        <properties><name>lon-dc01</name></properties>
+        <gatewaySettings inherit="None"><enabled>False</enabled></gatewaySettings>
+      </server>
+    </group>
+    <server><properties><displayName>nameless</displayName></properties></server>
+  </file>
+</RDCMan>"#;
+
+        let mut skipped = Vec::new();
+        let hosts = parse_rdcman(xml, &mut skipped).unwrap();
+        assert_eq!(
+            hosts,
+            vec![
+                Host {
+                    description: "SQL node".to_string(),
+                    group: "London".to_string(),
+                    port: Some(3390),
+                    gateway: "rdgw.example.com".to_string(),
+                    ..host("lon-sql01")
+                },
+                Host { group: "London".to_string(), port: Some(3390), ..host("lon-dc01") },
+            ]
+        );
+        assert_eq!(skipped, vec!["nameless: no hostname"]);
+    }
+
+    #[test]
+    fn reads_royalts_folders_and_paths() {
+        let json = r#"{"Objects": [
+            {"Type": "Folder", "Name": "London", "Objects": [
+                {"Type": "RemoteDesktopConnection", "Name": "sql", "ComputerName": "lon-sql01",
+                 "Description": "SQL node", "Port": "3390", "Path": "SQL"},
+                {"Type": "SSHConnection", "Name": "switch", "ComputerName": "lon-sw01"}
+            ]},
+            {"Name": "dc", "ComputerName": "lon-dc01", "Port": 3389, "GatewayHostName": "rdgw.example.com"}
+        ]}"#;
+
+        let mut skipped = Vec::new();
+        let hosts = parse_royalts(json, &mut skipped).unwrap();
+        assert_eq!(
+            hosts,
+            vec![
+                Host {
+                    description: "SQL node".to_string(),
+                    group: "London/SQL".to_string(),
+                    port: Some(3390),
+                    ..host("lon-sql01")
+                },
+                Host { gateway: "rdgw.example.com".to_string(), ..host("lon-dc01") },
+            ]
+        );
+        assert_eq!(skipped, vec!["switch: SSHConnection objects are not supported"]);
+    }
+}
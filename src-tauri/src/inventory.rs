@@ -0,0 +1,170 @@
+use crate::csv_loader::{self, LoadReport, UnparsedEntry};
+use crate::{csv_dialect, settings, Host};
+use std::path::{Path, PathBuf};
+
+// Bumped whenever the JSON/YAML layout changes in a way older versions can't read
+const INVENTORY_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InventoryFormat {
+    Csv,
+    Json,
+    Yaml,
+}
+
+impl InventoryFormat {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "csv" | "txt" => Ok(InventoryFormat::Csv),
+            "json" => Ok(InventoryFormat::Json),
+            "yaml" | "yml" => Ok(InventoryFormat::Yaml),
+            _ => Err(format!(
+                "Unsupported inventory file {}, use a .csv, .json or .yaml file",
+                path.display()
+            )),
+        }
+    }
+}
+
+// Layout of hosts.json and hosts.yaml, described by schema/inventory.schema.json
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct InventoryDocument<T> {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    hosts: Vec<T>,
+}
+
+// The inventory file in use, hosts.csv unless settings point somewhere else
+pub fn path() -> PathBuf {
+    PathBuf::from(settings::load().inventory_file)
+}
+
+// Load every valid host from an inventory file in any of the supported
+// formats. Like the CSV loader, bad entries are reported rather than fatal.
+pub fn load(path: &Path) -> Result<LoadReport, String> {
+    let format = InventoryFormat::from_path(path)?;
+    if format == InventoryFormat::Csv {
+        return csv_loader::load_hosts_csv(path);
+    }
+
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let contents = csv_dialect::decode(&bytes);
+
+    // Hosts stay untyped until each one is checked on its own below
+    let document: InventoryDocument<serde_json::Value> = match format {
+        InventoryFormat::Json => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?,
+        _ => serde_yaml_ng::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?,
+    };
+    if document.version > INVENTORY_VERSION {
+        return Err(format!(
+            "{} is inventory version {}, this version of ConnectX only reads up to {}",
+            path.display(),
+            document.version,
+            INVENTORY_VERSION
+        ));
+    }
+
+    // A single bad host shouldn't hide the rest
+    let mut report = LoadReport::default();
+    for (index, value) in document.hosts.into_iter().enumerate() {
+        let line = index as u64 + 1;
+        match serde_json::from_value::<Host>(value.clone()) {
+            Ok(host) => report.push_host(line, host, || UnparsedEntry::Value(value)),
+            Err(e) => report.push_problem(line, format!("Invalid host entry: {}", e), UnparsedEntry::Value(value)),
+        }
+    }
+
+    Ok(report)
+}
+
+// Serialize hosts in the format matching the file extension. Entries that
+// couldn't be loaded go after them, so they survive until fixed by hand.
+pub fn serialize(path: &Path, hosts: &[Host], unparsed: &[UnparsedEntry]) -> Result<Vec<u8>, String> {
+    let format = InventoryFormat::from_path(path)?;
+    if format == InventoryFormat::Csv {
+        return csv_loader::hosts_to_csv(hosts, unparsed, &settings::load().csv_output);
+    }
+
+    let mut entries = hosts
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to serialize hosts: {}", e))?;
+    entries.extend(unparsed.iter().map(UnparsedEntry::to_value));
+    let document = InventoryDocument { version: INVENTORY_VERSION, hosts: entries };
+
+    match format {
+        InventoryFormat::Json => serde_json::to_vec_pretty(&document)
+            .map_err(|e| format!("Failed to serialize hosts: {}", e)),
+        _ => serde_yaml_ng::to_string(&document)
+            .map(String::into_bytes)
+            .map_err(|e| format!("Failed to serialize hosts: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{full_host, host, temp_path};
+
+    // Only what every format has always had
+    fn minimal_host() -> Host {
+        host("ams-web01")
+    }
+
+    // Write the hosts and any unparsed entries to `path` and load them back
+    fn write_and_load(path: &Path, hosts: &[Host], unparsed: &[UnparsedEntry]) -> LoadReport {
+        std::fs::write(path, serialize(path, hosts, unparsed).unwrap()).unwrap();
+        let report = load(path);
+        std::fs::remove_file(path).unwrap();
+        report.unwrap()
+    }
+
+    fn round_trip_through_csv(extension: &str) {
+        let hosts = vec![full_host(), minimal_host()];
+
+        let original = write_and_load(&temp_path(&format!("original.{}", extension)), &hosts, &[]);
+        assert!(original.problems.is_empty(), "{:?}", original.problems);
+        assert_eq!(original.hosts, hosts);
+
+        let csv = write_and_load(&temp_path(&format!("{}.csv", extension)), &original.hosts, &[]);
+        assert!(csv.problems.is_empty(), "{:?}", csv.problems);
+        assert_eq!(csv.hosts, hosts);
+
+        let back = write_and_load(&temp_path(&format!("back.{}", extension)), &csv.hosts, &[]);
+        assert!(back.problems.is_empty(), "{:?}", back.problems);
+        assert_eq!(back.hosts, hosts);
+    }
+
+    #[test]
+    fn json_round_trips_through_csv() {
+        round_trip_through_csv("json");
+    }
+
+    #[test]
+    fn yaml_round_trips_through_csv() {
+        round_trip_through_csv("yaml");
+    }
+
+    #[test]
+    fn unparsed_entries_survive_a_format_change() {
+        let bad = serde_json::json!({ "hostname": "srv03", "description": "bad port", "port": "abc" });
+        let unparsed = [UnparsedEntry::Value(bad.clone())];
+
+        let csv = write_and_load(&temp_path("unparsed.csv"), &[minimal_host()], &unparsed);
+        assert_eq!(csv.hosts, vec![minimal_host()]);
+        assert_eq!(csv.problems.len(), 1);
+
+        let json = write_and_load(&temp_path("unparsed.json"), &csv.hosts, &csv.unparsed);
+        assert_eq!(json.hosts, vec![minimal_host()]);
+        assert_eq!(json.unparsed.iter().map(UnparsedEntry::to_value).collect::<Vec<_>>(), vec![bad]);
+    }
+}
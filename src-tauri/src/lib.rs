@@ -20,12 +20,41 @@ use tauri::{
     menu::{Menu, MenuItem},
     tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState},
 };
+use std::collections::BTreeMap;
 use std::sync::Mutex;
 use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::fs::OpenOptions;
 use std::io::Write;
 
+mod csv_dialect;
+mod csv_loader;
+mod diff;
+mod duplicates;
+mod exporters;
+mod folders;
+mod history;
+mod importers;
+mod inventory;
+mod query;
+mod rdp;
+mod recent;
+mod rules;
+mod saved_searches;
+mod search;
+mod search_index;
+mod settings;
+mod snapshots;
+mod staging;
+mod storage;
+#[cfg(test)]
+mod test_support;
+mod trash;
+mod usage;
+mod validation;
+mod watcher;
+mod xlsx;
+
 static LAST_HIDDEN_WINDOW: Mutex<String> = Mutex::new(String::new());
 static HOSTS: Mutex<Vec<Host>> = Mutex::new(Vec::new());
 
@@ -41,17 +70,70 @@ struct StoredCredentials {
     password: String,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 struct Host {
     hostname: String,
     description: String,
+    // Folder path such as "London/SQL", empty for ungrouped hosts
+    #[serde(default)]
+    group: String,
+    // Only set when the host doesn't listen on the default RDP port
+    #[serde(default)]
+    port: Option<u16>,
+    // Remote Desktop Gateway to connect through, empty for a direct connection
+    #[serde(default)]
+    gateway: String,
+    // Other names the server is known by: a CNAME, an old name, the cluster name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+    // Operating system as AD reports it, e.g. "Windows Server 2019 Standard"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    os: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    // Custom fields such as site or role, usually derived by the tag rules
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, String>,
+    // Starred in the main window, favorites are listed when nothing is searched
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    favorite: bool,
+    // Overrides the RDP settings inherited from the host's folders
+    #[serde(default, skip_serializing_if = "rdp::RdpSettings::is_empty")]
+    rdp: rdp::RdpSettings,
 }
 
 #[tauri::command]
 async fn save_credentials(credentials: Credentials) -> Result<(), String> {
+    write_credential("ConnectX", &credentials)
+}
+
+// Credential profiles are stored next to the default credentials as ConnectX/<profile>
+fn profile_credential_target(profile: &str) -> String {
+    format!("ConnectX/{}", profile)
+}
+
+#[tauri::command]
+async fn save_profile_credentials(profile: String, credentials: Credentials) -> Result<(), String> {
+    if profile.trim().is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    write_credential(&profile_credential_target(profile.trim()), &credentials)
+}
+
+#[tauri::command]
+async fn get_profile_credentials(profile: String) -> Result<Option<StoredCredentials>, String> {
+    read_credential(&profile_credential_target(profile.trim()))
+}
+
+#[tauri::command]
+async fn delete_profile_credentials(profile: String) -> Result<(), String> {
+    remove_credential(&profile_credential_target(profile.trim()))
+}
+
+fn write_credential(target: &str, credentials: &Credentials) -> Result<(), String> {
     unsafe {
         // Convert strings to wide character format
-        let target_name: Vec<u16> = OsStr::new("ConnectX")
+        let target_name: Vec<u16> = OsStr::new(target)
             .encode_wide()
             .chain(std::iter::once(0))
             .collect();
@@ -83,26 +165,43 @@ async fn save_credentials(credentials: Credentials) -> Result<(), String> {
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SearchMode {
+    // Fuzzy text with field terms, see query.rs
+    #[default]
+    Query,
+    // One regular expression, optionally limited to `field`
+    Regex,
+}
+
 #[tauri::command]
-async fn search_hosts(query: String) -> Result<Vec<Host>, String> {
-    let hosts = get_hosts()?;
-    let query = query.to_lowercase();
-    
-    let filtered_hosts: Vec<Host> = hosts
-        .into_iter()
-        .filter(|host| {
-            host.hostname.to_lowercase().contains(&query) ||
-            host.description.to_lowercase().contains(&query)
-        })
-        .collect();
-    
-    Ok(filtered_hosts)
+async fn search_hosts(
+    query: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    mode: Option<SearchMode>,
+    field: Option<String>,
+) -> Result<search_index::SearchPage, String> {
+    // Fuzzy matches ranked by score, so "sq01" finds LON-PRD-SQL01 first.
+    // Field terms (tag:prod, os:2019) narrow the results down.
+    let custom_fields = search_index::custom_field_names()?;
+    let query = match mode.unwrap_or_default() {
+        SearchMode::Query => query::parse(&query, &custom_fields)?,
+        SearchMode::Regex if query.is_empty() => None,
+        SearchMode::Regex => Some(query::regex(&query, field.as_deref(), &custom_fields)?),
+    };
+    search_index::search(query, limit, offset.unwrap_or(0))
 }
 
 #[tauri::command]
 async fn get_stored_credentials() -> Result<Option<StoredCredentials>, String> {
+    read_credential("ConnectX")
+}
+
+fn read_credential(target: &str) -> Result<Option<StoredCredentials>, String> {
     unsafe {
-        let target_name: Vec<u16> = OsStr::new("ConnectX")
+        let target_name: Vec<u16> = OsStr::new(target)
             .encode_wide()
             .chain(std::iter::once(0))
             .collect();
@@ -135,8 +234,12 @@ async fn get_stored_credentials() -> Result<Option<StoredCredentials>, String> {
 
 #[tauri::command]
 async fn delete_credentials() -> Result<(), String> {
+    remove_credential("ConnectX")
+}
+
+fn remove_credential(target: &str) -> Result<(), String> {
     unsafe {
-        let target_name: Vec<u16> = OsStr::new("ConnectX")
+        let target_name: Vec<u16> = OsStr::new(target)
             .encode_wide()
             .chain(std::iter::once(0))
             .collect();
@@ -294,120 +397,208 @@ async fn hide_hosts_window(app_handle: tauri::AppHandle) -> Result<(), String> {
 
 #[tauri::command]
 fn get_hosts() -> Result<Vec<Host>, String> {
-    let path = std::path::Path::new("hosts.csv");
+    let path = inventory::path();
     if !path.exists() {
         return Ok(Vec::new());
     }
 
-    let contents = std::fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read CSV: {}", e))?;
-
-    let mut hosts = Vec::new();
-    let mut reader = csv::ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(contents.as_bytes());
-
-    for result in reader.records() {
-        match result {
-            Ok(record) => {
-                if record.len() >= 2 {
-                    hosts.push(Host {
-                        hostname: record[0].to_string(),
-                        description: record[1].to_string(),
-                    });
-                }
-            }
-            Err(e) => return Err(format!("Failed to parse CSV record: {}", e)),
-        }
+    read_hosts_file(&path)
+}
+
+// Load the valid rows of a hosts file. Rows that can't be loaded are logged
+// and skipped, validate_hosts_file lists them with line numbers.
+fn read_hosts_file(path: &std::path::Path) -> Result<Vec<Host>, String> {
+    let report = inventory::load(path)?;
+
+    if !report.problems.is_empty() {
+        log_to_file(&format!(
+            "Skipped {} invalid row(s) in {}, run validate_hosts_file for details",
+            report.problems.len(),
+            path.display()
+        ));
     }
 
-    Ok(hosts)
+    Ok(report.hosts)
 }
 
-#[tauri::command]
-fn save_host(host: Host) -> Result<(), String> {
-    // Create hosts.csv if it doesn't exist
-    if !std::path::Path::new("hosts.csv").exists() {
-        let mut wtr = csv::WriterBuilder::new()
-            .from_path("hosts.csv")
-            .map_err(|e| format!("Failed to create hosts.csv: {}", e))?;
-        
-        wtr.write_record(&["hostname", "description"])
-            .map_err(|e| format!("Failed to write CSV header: {}", e))?;
-        
-        wtr.flush()
-            .map_err(|e| format!("Failed to flush CSV writer: {}", e))?;
+// Replace the hosts in the inventory file. Rows of the file that couldn't be
+// loaded aren't in `hosts`, they are written back as they were so editing a
+// host doesn't throw them away.
+fn write_hosts(hosts: &[Host]) -> Result<(), String> {
+    let path = inventory::path();
+    let unparsed = if path.exists() {
+        let report = inventory::load(&path)?;
+        if report.unreadable > 0 {
+            return Err(format!(
+                "{} has {} row(s) that can't be read at all, fix them before making changes",
+                path.display(),
+                report.unreadable
+            ));
+        }
+        report.unparsed
+    } else {
+        Vec::new()
+    };
+    write_inventory(hosts, &unparsed)
+}
+
+// Replace the whole inventory file, including the rows that couldn't be loaded
+fn write_inventory(hosts: &[Host], unparsed: &[csv_loader::UnparsedEntry]) -> Result<(), String> {
+    let path = inventory::path();
+    let contents = inventory::serialize(&path, hosts, unparsed)?;
+
+    write_file_atomic(&path, &contents)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    search_index::update(hosts);
+
+    // Keep the watcher's copy current so our own save isn't reported as an external edit
+    match HOSTS.lock() {
+        Ok(mut hosts_data) => *hosts_data = hosts.to_vec(),
+        Err(_) => log_to_file("Failed to acquire hosts lock after writing the inventory"),
     }
+    Ok(())
+}
 
+#[tauri::command]
+fn save_host(host: Host, previous_hostname: Option<String>) -> Result<(), String> {
     let mut hosts = get_hosts()?;
     
-    // Check if hostname is empty or invalid
-    if host.hostname.trim().is_empty() {
-        return Err("Hostname cannot be empty".to_string());
-    }
+    // Check if hostname, port and gateway are valid
+    let host = validation::normalize_host(&host)
+        .map_err(|errors| validation::describe(&errors))?;
+    let host = rules::apply_rules(&host, &rules::load_rules());
     
-    // Update or add the host
-    if let Some(idx) = hosts.iter().position(|h| h.hostname == host.hostname) {
-        hosts[idx] = host;
-    } else {
-        hosts.push(host);
-    }
-
-    let mut wtr = csv::WriterBuilder::new()
-        .from_path("hosts.csv")
-        .map_err(|e| format!("Failed to create CSV writer: {}", e))?;
+    log_to_file(&format!("Saving host: {} - {}", host.hostname, host.description));
 
-    // Write header
-    wtr.write_record(&["hostname", "description"])
-        .map_err(|e| format!("Failed to write CSV header: {}", e))?;
-
-    // Write records
-    for host in hosts {
-        log_to_file(&format!("Writing host to CSV: {} - {}", host.hostname, host.description));
-        wtr.write_record(&[&host.hostname, &host.description])
-            .map_err(|e| format!("Failed to write CSV record: {}", e))?;
+    // previous_hostname is set when an existing host is renamed
+    let existing_name = previous_hostname.unwrap_or_else(|| host.hostname.clone());
+    if !existing_name.eq_ignore_ascii_case(&host.hostname)
+        && hosts.iter().any(|h| h.hostname.eq_ignore_ascii_case(&host.hostname))
+    {
+        return Err(format!("{} already exists", host.hostname));
+    }
+    // A name shared with another host would search and connect to the wrong server
+    let others = hosts.iter().filter(|h| !h.hostname.eq_ignore_ascii_case(&existing_name));
+    if let Some(conflict) = validation::name_conflict(&host, others) {
+        return Err(conflict);
     }
 
-    wtr.flush()
-        .map_err(|e| format!("Failed to flush CSV writer: {}", e))?;
+    // Update or add the host. Hostnames are case-insensitive, so SRV01 and
+    // srv01 are the same entry.
+    if let Some(idx) = hosts.iter().position(|h| h.hostname.eq_ignore_ascii_case(&existing_name)) {
+        let old = std::mem::replace(&mut hosts[idx], host.clone());
+        write_hosts(&hosts)?;
+        history::record("updated", Some(&old), Some(&host));
+    } else {
+        hosts.push(host.clone());
+        write_hosts(&hosts)?;
+        history::record("created", None, Some(&host));
+    }
 
     Ok(())
 }
 
 #[tauri::command]
 fn delete_host(hostname: String) -> Result<(), String> {
-    let hosts: Vec<Host> = get_hosts()?
+    let (deleted, hosts): (Vec<Host>, Vec<Host>) = get_hosts()?
         .into_iter()
-        .filter(|h| h.hostname != hostname)
-        .collect();
-
-    let mut wtr = csv::WriterBuilder::new()
-        .from_path("hosts.csv")
-        .map_err(|e| format!("Failed to create CSV writer: {}", e))?;
+        .partition(|h| h.hostname.eq_ignore_ascii_case(&hostname));
+    if deleted.is_empty() {
+        return Err(format!("Host not found: {}", hostname));
+    }
 
-    // Write header
-    wtr.write_record(&["hostname", "description"])
-        .map_err(|e| format!("Failed to write CSV header: {}", e))?;
+    snapshots::create_snapshot("delete-host")?;
 
-    // Write records
-    for host in hosts {
-        wtr.write_record(&[&host.hostname, &host.description])
-            .map_err(|e| format!("Failed to write CSV record: {}", e))?;
-    }
+    trash::move_to_trash(&deleted)?;
+    write_hosts(&hosts)?;
+    history::record_all("deleted", &deleted, &[]);
+    Ok(())
+}
 
-    wtr.flush()
-        .map_err(|e| format!("Failed to flush CSV writer: {}", e))?;
+// Star or unstar a host
+#[tauri::command]
+fn set_favorite(hostname: String, favorite: bool) -> Result<(), String> {
+    let mut hosts = get_hosts()?;
+    mark_favorite(&mut hosts, &hostname, favorite)?;
+    write_hosts(&hosts)
+}
 
+fn mark_favorite(hosts: &mut [Host], hostname: &str, favorite: bool) -> Result<(), String> {
+    let host = hosts
+        .iter_mut()
+        .find(|h| h.hostname.eq_ignore_ascii_case(hostname))
+        .ok_or(format!("Host not found: {}", hostname))?;
+    host.favorite = favorite;
     Ok(())
 }
 
+// Starred hosts in inventory order, the main window lists them until
+// something is searched
+#[tauri::command]
+fn get_favorites() -> Result<Vec<Host>, String> {
+    Ok(get_hosts()?.into_iter().filter(|h| h.favorite).collect())
+}
 
+// The name mstsc connects to. Usage and settings always go by the host's
+// own name whichever is picked.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ConnectTarget {
+    #[default]
+    Hostname,
+    // One of the host's aliases
+    Alias(String),
+    // The address the hostname resolves to, for when DNS names don't work
+    // with the server's certificate or Kerberos setup
+    Ip,
+}
+
+fn connect_address(host: &Host, target: &ConnectTarget) -> Result<String, String> {
+    match target {
+        ConnectTarget::Hostname => Ok(host.hostname.clone()),
+        ConnectTarget::Alias(alias) => host
+            .aliases
+            .iter()
+            .find(|a| a.eq_ignore_ascii_case(alias))
+            .cloned()
+            .ok_or(format!("{} is not an alias of {}", alias, host.hostname)),
+        ConnectTarget::Ip => {
+            // Prefer IPv4 when the name has both
+            let addresses = duplicates::resolve_addresses(&host.hostname);
+            addresses
+                .iter()
+                .find(|ip| ip.is_ipv4())
+                .or(addresses.first())
+                .map(|ip| ip.to_string())
+                .ok_or(format!("Failed to resolve {} to an IP address", host.hostname))
+        }
+    }
+}
 
 #[tauri::command]
-async fn launch_rdp(host: Host) -> Result<(), String> {
-    // Get stored credentials
-    let credentials = get_stored_credentials().await?
-        .ok_or("No stored credentials found".to_string())?;
+async fn launch_rdp(host: Host, target: Option<ConnectTarget>) -> Result<(), String> {
+    // Fill in gateway, port and RDP settings inherited from the host's folders
+    let resolved = folders::resolve_host(&host)?;
+    let canonical_name = resolved.host.hostname.clone();
+    let mut host = resolved.host;
+    host.hostname = connect_address(&host, &target.unwrap_or_default())?;
+
+    // Use the folder's credential profile when it has one saved, the default credentials otherwise
+    let profile_credentials = if resolved.credential_profile.is_empty() {
+        None
+    } else {
+        read_credential(&profile_credential_target(&resolved.credential_profile))?
+    };
+    // The profile in use, empty when falling back to the default credentials
+    let credential_profile = match profile_credentials {
+        Some(_) => resolved.credential_profile.clone(),
+        None => String::new(),
+    };
+    let credentials = match profile_credentials {
+        Some(credentials) => credentials,
+        None => get_stored_credentials().await?
+            .ok_or("No stored credentials found".to_string())?,
+    };
     
     unsafe {
         // Convert password to wide string (UTF-16) as Windows expects
@@ -440,15 +631,10 @@ async fn launch_rdp(host: Host) -> Result<(), String> {
             UserName: PWSTR(username.as_ptr() as *mut u16),
         };
 
-        // For debugging
-        println!("Saving credential for target: TERMSRV/{}", host.hostname);
-        println!("Username length: {}", credentials.username.len());
-        println!("Password length: {}", credentials.password.len());
-
-        match CredWriteW(&cred, 0) {
-            Ok(_) => println!("Successfully saved credential"),
-            Err(e) => return Err(format!("Failed to save RDP credentials: {:?}", e)),
+        if let Err(e) = CredWriteW(&cred, 0) {
+            return Err(format!("Failed to save RDP credentials: {:?}", e));
         }
+        log_to_file(&format!("Saved RDP credentials for TERMSRV/{}", host.hostname));
     }
 
     // Create filename with hostname and timestamp
@@ -457,60 +643,11 @@ async fn launch_rdp(host: Host) -> Result<(), String> {
         .unwrap()
         .as_secs();
     let temp_dir = std::env::temp_dir();
-    let rdp_path = temp_dir.join(format!("{}_{}.rdp", host.hostname, timestamp));
+    // Named after the host rather than the address, an IPv6 address isn't a valid file name
+    let rdp_path = temp_dir.join(format!("{}_{}.rdp", canonical_name, timestamp));
     
     // Create RDP file content
-    let rdp_content = format!(
-        "screen mode id:i:2\r\n\
-         desktopwidth:i:1920\r\n\
-         desktopheight:i:1080\r\n\
-         session bpp:i:32\r\n\
-         full address:s:{}\r\n\
-         compression:i:1\r\n\
-         keyboardhook:i:2\r\n\
-         audiocapturemode:i:1\r\n\
-         videoplaybackmode:i:1\r\n\
-         connection type:i:2\r\n\
-         networkautodetect:i:1\r\n\
-         bandwidthautodetect:i:1\r\n\
-         enableworkspacereconnect:i:1\r\n\
-         disable wallpaper:i:0\r\n\
-         allow desktop composition:i:0\r\n\
-         allow font smoothing:i:0\r\n\
-         disable full window drag:i:1\r\n\
-         disable menu anims:i:1\r\n\
-         disable themes:i:0\r\n\
-         disable cursor setting:i:0\r\n\
-         bitmapcachepersistenable:i:1\r\n\
-         audiomode:i:0\r\n\
-         redirectprinters:i:1\r\n\
-         redirectcomports:i:0\r\n\
-         redirectsmartcards:i:1\r\n\
-         redirectclipboard:i:1\r\n\
-         redirectposdevices:i:0\r\n\
-         autoreconnection enabled:i:1\r\n\
-         authentication level:i:2\r\n\
-         prompt for credentials:i:0\r\n\
-         negotiate security layer:i:1\r\n\
-         remoteapplicationmode:i:0\r\n\
-         alternate shell:s:\r\n\
-         shell working directory:s:\r\n\
-         gatewayhostname:s:\r\n\
-         gatewayusagemethod:i:4\r\n\
-         gatewaycredentialssource:i:4\r\n\
-         gatewayprofileusagemethod:i:0\r\n\
-         promptcredentialonce:i:1\r\n\
-         use redirection server name:i:0\r\n\
-         rdgiskdcproxy:i:0\r\n\
-         kdcproxyname:s:\r\n\
-         username:s:{}\r\n\
-         domain:s:\r\n\
-         enablecredsspsupport:i:1\r\n\
-         public mode:i:0\r\n\
-         cert ignore:i:1",
-        host.hostname,
-        credentials.username
-    );
+    let rdp_content = rdp::build_rdp_file(&host, &resolved.rdp, &credentials.username);
 
     // Write the RDP file
     std::fs::write(&rdp_path, rdp_content)
@@ -522,6 +659,11 @@ async fn launch_rdp(host: Host) -> Result<(), String> {
         .spawn()
         .map_err(|e| format!("Failed to launch RDP: {}", e))?;
     
+    // Hosts connected to often and recently come first in searches, and the
+    // latest tops the recent connections
+    usage::record_connection(&canonical_name);
+    recent::record_connection(&canonical_name, &host.hostname, &credential_profile);
+
     // Give mstsc time to read the file
     std::thread::sleep(std::time::Duration::from_secs(1));
     
@@ -532,6 +674,17 @@ async fn launch_rdp(host: Host) -> Result<(), String> {
     Ok(())
 }
 
+// Write to a temporary file first and rename it over the target, so readers
+// (including the inventory watcher) never see a half-written file
+fn write_file_atomic(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = std::path::PathBuf::from(tmp_path);
+
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)
+}
+
 fn log_to_file(message: &str) {
     let log_path = "connectx.log";
     if let Ok(mut file) = OpenOptions::new()
@@ -551,11 +704,15 @@ fn log_to_file(message: &str) {
 }
 
 #[tauri::command]
-async fn scan_domain(app_handle: tauri::AppHandle, _domain: String, server: String) -> Result<String, String> {
+async fn scan_domain(app_handle: tauri::AppHandle, _domain: String, server: String) -> Result<staging::StagedChanges, String> {
     // Get the hosts window and set it to always on top temporarily
     let hosts_window = app_handle.get_webview_window("hosts")
         .ok_or("Failed to get hosts window".to_string())?;
     
+    // Export-Csv can only write CSV, so scan into a scratch file and stage the
+    // result for review rather than overwriting the inventory
+    let scan_path = std::env::temp_dir().join("connectx-domain-scan.csv");
+
     // Set window to always on top
     hosts_window.set_always_on_top(true)
         .map_err(|_| "Failed to set window always on top".to_string())?;
@@ -565,9 +722,10 @@ async fn scan_domain(app_handle: tauri::AppHandle, _domain: String, server: Stri
         "Import-Module ActiveDirectory; \
          Get-ADComputer -Server '{}' -Filter 'OperatingSystem -like \"*Windows Server*\"' -Properties DNSHostName,Description,OperatingSystem | \
          Where-Object {{$_.DNSHostName}} | \
-         Select-Object @{{Name='hostname';Expression={{$_.DNSHostName}}}}, @{{Name='description';Expression={{$_.Description}}}} | \
-         Export-Csv -Path 'hosts.csv' -NoTypeInformation -Force",
-        server
+         Select-Object @{{Name='hostname';Expression={{$_.DNSHostName}}}}, @{{Name='description';Expression={{$_.Description}}}}, @{{Name='os';Expression={{$_.OperatingSystem}}}} | \
+         Export-Csv -Path '{}' -NoTypeInformation -Force",
+        server,
+        scan_path.display().to_string().replace('\'', "''")
     );
 
     let result = Command::new("powershell")
@@ -593,16 +751,39 @@ async fn scan_domain(app_handle: tauri::AppHandle, _domain: String, server: Stri
                 return Err(format!("Failed to scan domain. Error: {}", error));
             }
 
-            let found_servers = match std::fs::read_to_string("hosts.csv") {
-                Ok(contents) => contents.lines().count().saturating_sub(1),
-                Err(_) => 0
-            };
+            // The loader runs the scanned names through the same checks as hosts added by hand
+            let report = csv_loader::load_hosts_csv(&scan_path);
+            let _ = std::fs::remove_file(&scan_path);
+            let report = report?;
+
+            if report.hosts.is_empty() {
+                return Err("No Windows Servers found in the domain.".to_string());
+            }
 
-            if found_servers == 0 {
-                Err("No Windows Servers found in the domain.".to_string())
-            } else {
-                Ok(format!("Successfully found {} Windows Server(s).", found_servers))
+            let skipped = report
+                .problems
+                .iter()
+                .map(|problem| format!("Line {}: {}", problem.line, problem.reason))
+                .collect();
+
+            // AD only knows names, descriptions and the OS, everything else
+            // set up locally stays as it is
+            let local = get_hosts()?;
+            let mut found_hosts = report.hosts;
+            for host in found_hosts.iter_mut() {
+                if let Some(existing) = local.iter().find(|h| h.hostname.eq_ignore_ascii_case(&host.hostname)) {
+                    host.group = existing.group.clone();
+                    host.port = existing.port;
+                    host.gateway = existing.gateway.clone();
+                    host.aliases = existing.aliases.clone();
+                    host.tags = existing.tags.clone();
+                    host.fields = existing.fields.clone();
+                    host.favorite = existing.favorite;
+                    host.rdp = existing.rdp.clone();
+                }
             }
+
+            staging::stage(&format!("domain scan of {}", server), staging::StageMode::Replace, found_hosts, skipped)
         },
         Err(e) => Err(e)
     }
@@ -617,7 +798,7 @@ fn initialize_hosts() -> Result<(), String> {
             },
             Err(e) => {
                 // If the file doesn't exist, that's okay - we'll start with an empty Vec
-                if !std::path::Path::new("hosts.csv").exists() {
+                if !inventory::path().exists() {
                     *hosts_data = Vec::new();
                     Ok(())
                 } else {
@@ -705,9 +886,15 @@ async fn get_host_credentials(hostname: String) -> Result<Option<StoredCredentia
 
 #[tauri::command]
 async fn delete_all_hosts() -> Result<(), String> {
-    // Create empty file to clear all contents
-    std::fs::write("hosts.csv", "hostname,description\n")
+    snapshots::create_snapshot("delete-all")?;
+    let deleted = get_hosts()?;
+    trash::move_to_trash(&deleted)?;
+
+    // Rows that couldn't be loaded aren't hosts ConnectX knows about, those
+    // stay until they are fixed or removed by hand
+    write_hosts(&[])
         .map_err(|e| format!("Failed to clear hosts file: {}", e))?;
+    history::record_all("deleted", &deleted, &[]);
     Ok(())
 }
 
@@ -879,6 +1066,9 @@ pub fn run() {
                 eprintln!("Warning: Failed to initialize hosts: {}", e);
                 log_to_file(&format!("Failed to initialize hosts: {}", e));
             }
+
+            // Pick up edits made to the inventory outside ConnectX
+            watcher::watch_hosts_file(app.app_handle().clone());
             
             Ok(())
         })
@@ -897,6 +1087,8 @@ pub fn run() {
             get_hosts,
             save_host,
             delete_host,
+            set_favorite,
+            get_favorites,
             hide_hosts_window,
             search_hosts,
             launch_rdp,
@@ -904,7 +1096,95 @@ pub fn run() {
             save_host_credentials,
             get_host_credentials,
             delete_all_hosts,
+            snapshots::list_snapshots,
+            snapshots::diff_snapshot,
+            snapshots::restore_snapshot,
+            importers::import_hosts,
+            exporters::export_hosts,
+            save_profile_credentials,
+            get_profile_credentials,
+            delete_profile_credentials,
+            folders::get_folder_tree,
+            folders::create_folder,
+            folders::rename_folder,
+            folders::move_folder,
+            folders::move_host,
+            folders::set_folder_defaults,
+            duplicates::find_duplicate_hosts,
+            duplicates::merge_hosts,
+            validation::validate_host,
+            csv_loader::validate_hosts_file,
+            settings::get_settings,
+            settings::save_settings,
+            xlsx::list_xlsx_sheets,
+            xlsx::import_xlsx,
+            staging::stage_hosts_file,
+            staging::get_staged_changes,
+            staging::apply_staged_changes,
+            staging::discard_staged_changes,
+            trash::list_deleted_hosts,
+            trash::restore_host,
+            trash::purge_deleted,
+            history::get_host_history,
+            history::revert_change,
+            rules::get_tag_rules,
+            rules::save_tag_rules,
+            rules::apply_tag_rules,
+            saved_searches::list_saved_searches,
+            saved_searches::create_saved_search,
+            saved_searches::update_saved_search,
+            saved_searches::delete_saved_search,
+            saved_searches::get_saved_search_hosts,
+            saved_searches::launch_saved_search,
+            saved_searches::export_saved_search,
+            recent::get_recent_connections,
+            recent::clear_recent_connections,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{host, strings};
+
+    #[test]
+    fn connects_by_hostname_alias_or_address() {
+        let host = Host { aliases: strings(&["SQLCluster"]), ..host("10.0.0.5") };
+        assert_eq!(connect_address(&host, &ConnectTarget::Hostname).unwrap(), "10.0.0.5");
+        assert_eq!(connect_address(&host, &ConnectTarget::Alias("sqlcluster".to_string())).unwrap(), "SQLCluster");
+        assert!(connect_address(&host, &ConnectTarget::Alias("web".to_string())).is_err());
+        assert_eq!(connect_address(&host, &ConnectTarget::Ip).unwrap(), "10.0.0.5");
+    }
+
+    #[test]
+    fn reads_connect_targets_sent_by_the_frontend() {
+        let target = |json| serde_json::from_value::<ConnectTarget>(json).unwrap();
+        assert_eq!(target(serde_json::json!("hostname")), ConnectTarget::Hostname);
+        assert_eq!(target(serde_json::json!("ip")), ConnectTarget::Ip);
+        assert_eq!(target(serde_json::json!({ "alias": "sqlcluster" })), ConnectTarget::Alias("sqlcluster".to_string()));
+    }
+
+    #[test]
+    fn stars_hosts_by_name_ignoring_case() {
+        let mut hosts = vec![host("lon-sql01"), host("ams-web01")];
+        mark_favorite(&mut hosts, "AMS-WEB01", true).unwrap();
+        assert!(!hosts[0].favorite);
+        assert!(hosts[1].favorite);
+
+        mark_favorite(&mut hosts, "ams-web01", false).unwrap();
+        assert!(!hosts[1].favorite);
+        assert!(mark_favorite(&mut hosts, "par-app01", true).is_err());
+    }
+
+    #[test]
+    fn only_writes_the_favorite_flag_when_starred() {
+        let plain = serde_json::to_value(host("lon-sql01")).unwrap();
+        assert!(plain.get("favorite").is_none());
+
+        let starred = serde_json::to_value(Host { favorite: true, ..host("lon-sql01") }).unwrap();
+        assert_eq!(starred["favorite"], serde_json::json!(true));
+        assert!(serde_json::from_value::<Host>(plain).map(|h| !h.favorite).unwrap());
+    }
+}
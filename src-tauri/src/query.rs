@@ -0,0 +1,645 @@
+use crate::{search, Host};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+
+// Search syntax:
+//   sql01                 fuzzy match on hostname, aliases or description
+//   "domain controller"   exact phrase in hostname, aliases or description
+//   tag:prod  os:2019     field terms, a quoted value works too: desc:"file server"
+//   -tag:test             negation
+//   a OR b, (a OR b) c    alternatives; terms next to each other must all match
+// Any other name before ':' has to be a custom field some host has (site:LON).
+//
+// Regex mode skips all of this and matches one pattern, see `regex`.
+
+// Longer patterns are refused outright, and compiled ones are capped in size.
+// Matching itself always runs in linear time, the regex crate doesn't backtrack.
+const MAX_PATTERN_LEN: usize = 1000;
+const MAX_REGEX_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Hostname,
+    Alias,
+    Description,
+    Group,
+    Tag,
+    Os,
+    Port,
+    Gateway,
+    Custom(String),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Field {
+        match name.to_lowercase().as_str() {
+            "host" | "hostname" | "name" => Field::Hostname,
+            "alias" | "aliases" => Field::Alias,
+            "desc" | "description" => Field::Description,
+            "group" | "folder" => Field::Group,
+            "tag" | "tags" => Field::Tag,
+            "os" => Field::Os,
+            "port" => Field::Port,
+            "gateway" | "gw" => Field::Gateway,
+            _ => Field::Custom(name.to_string()),
+        }
+    }
+
+    // Like from_name, but a name that isn't a Host field has to be one of
+    // `custom_fields`, so a typo like "hostnme:" isn't quietly matched against
+    // a custom field nobody has
+    pub fn from_known_name(name: &str, custom_fields: &HashSet<String>) -> Result<Field, String> {
+        match Field::from_name(name) {
+            Field::Custom(name) if !custom_fields.contains(&name.to_lowercase()) => {
+                Err(format!("Unknown field '{}'", name))
+            }
+            field => Ok(field),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Query {
+    Text { value: String, phrase: bool },
+    // Without a field the hostname and description are searched
+    Regex { regex: Regex, field: Option<Field> },
+    Field { field: Field, value: String },
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    FieldWord(String, String),
+    FieldPhrase(String, String),
+}
+
+fn read_phrase(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    // The opening quote is already consumed
+    let mut phrase = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(phrase);
+        }
+        phrase.push(c);
+    }
+    Err(format!("Missing closing quote after \"{}", phrase))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Phrase(read_phrase(&mut chars)?));
+            }
+            '-' => {
+                chars.next();
+                match chars.peek() {
+                    Some(next) if !next.is_whitespace() && *next != ')' => tokens.push(Token::Not),
+                    _ => return Err("'-' has to be followed by the term to exclude".to_string()),
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                // field:value, or field:"a phrase". Anything else with a colon
+                // (an IPv6 address, srv01:3390) stays a plain word.
+                let field = word
+                    .split_once(':')
+                    .filter(|(name, value)| {
+                        !name.is_empty()
+                            && name.chars().all(|c| c.is_alphabetic() || c == '_' || c == '-')
+                            && !value.starts_with(':')
+                    })
+                    .map(|(name, value)| (name.to_string(), value.to_string()));
+
+                match field {
+                    Some((name, value)) if value.is_empty() && chars.peek() == Some(&'"') => {
+                        chars.next();
+                        tokens.push(Token::FieldPhrase(name, read_phrase(&mut chars)?));
+                    }
+                    Some((name, value)) if value.is_empty() => {
+                        return Err(format!("'{}:' needs a value", name));
+                    }
+                    Some((name, value)) => tokens.push(Token::FieldWord(name, value)),
+                    None if word == "OR" => tokens.push(Token::Or),
+                    None => tokens.push(Token::Word(word)),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    custom_fields: &'a HashSet<String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // alternatives := terms ("OR" terms)*
+    fn parse_or(&mut self) -> Result<Query, String> {
+        let mut alternatives = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            alternatives.push(self.parse_and()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.remove(0) } else { Query::Or(alternatives) })
+    }
+
+    // terms := term+
+    fn parse_and(&mut self) -> Result<Query, String> {
+        let mut terms = Vec::new();
+        while !matches!(self.peek(), None | Some(Token::Or) | Some(Token::Close)) {
+            terms.push(self.parse_term()?);
+        }
+        match terms.len() {
+            0 if self.peek() == Some(&Token::Close) => Err("Empty parentheses or a ')' without a matching '('".to_string()),
+            0 => Err("OR needs a term on both sides".to_string()),
+            1 => Ok(terms.remove(0)),
+            _ => Ok(Query::And(terms)),
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Query, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.parse_term()?))),
+            Some(Token::Open) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("Missing closing ')'".to_string()),
+                }
+            }
+            Some(Token::Word(value)) => Ok(Query::Text { value, phrase: false }),
+            Some(Token::Phrase(value)) => Ok(Query::Text { value, phrase: true }),
+            Some(Token::FieldWord(name, value)) | Some(Token::FieldPhrase(name, value)) => {
+                let field = Field::from_known_name(&name, self.custom_fields)?;
+                if field == Field::Port && value.parse::<u16>().is_err() {
+                    return Err(format!("'port:{}' is not a port number", value));
+                }
+                Ok(Query::Field { field, value })
+            }
+            _ => Err("Unexpected end of query".to_string()),
+        }
+    }
+}
+
+// Build a regex query, matched case-insensitively against one field or, by
+// default, the hostname and description. A field that isn't a Host field has
+// to be one of `custom_fields`, lowercase names of the custom fields in use.
+pub fn regex(pattern: &str, field: Option<&str>, custom_fields: &HashSet<String>) -> Result<Query, String> {
+    if pattern.len() > MAX_PATTERN_LEN {
+        return Err(format!("Regex is too long, the limit is {} characters", MAX_PATTERN_LEN));
+    }
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(MAX_REGEX_SIZE)
+        .dfa_size_limit(MAX_REGEX_SIZE)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => "Regex is too complex, simplify the pattern".to_string(),
+            e => format!("Invalid regex: {}", e),
+        })?;
+    let field = match field.map(str::trim).filter(|f| !f.is_empty()) {
+        Some(name) => Some(Field::from_known_name(name, custom_fields)?),
+        None => None,
+    };
+    if field == Some(Field::Port) {
+        return Err("Ports aren't text, search for port:<number> instead".to_string());
+    }
+    Ok(Query::Regex { regex, field })
+}
+
+// The hostname, each alias and the description, the texts free text is
+// matched against
+fn text_targets(host: &Host) -> Vec<(&'static str, Option<&str>, &str)> {
+    let aliases = host.aliases.iter().map(|alias| ("aliases", Some(alias.as_str()), alias.as_str()));
+    std::iter::once(("hostname", None, host.hostname.as_str()))
+        .chain(aliases)
+        .chain(std::iter::once(("description", None, host.description.as_str())))
+        .collect()
+}
+
+// The texts of a host a regex is matched against, with the field and key
+// they are reported under
+fn regex_targets<'a>(host: &'a Host, field: &Option<Field>) -> Vec<(&'static str, Option<&'a str>, &'a str)> {
+    match field {
+        None => text_targets(host),
+        Some(Field::Hostname) => vec![("hostname", None, &host.hostname)],
+        Some(Field::Alias) => host.aliases.iter().map(|alias| ("aliases", Some(alias.as_str()), alias.as_str())).collect(),
+        Some(Field::Description) => vec![("description", None, &host.description)],
+        Some(Field::Group) => vec![("group", None, &host.group)],
+        Some(Field::Os) => vec![("os", None, &host.os)],
+        Some(Field::Gateway) => vec![("gateway", None, &host.gateway)],
+        Some(Field::Tag) => host.tags.iter().map(|tag| ("tags", Some(tag.as_str()), tag.as_str())).collect(),
+        // Ports aren't text, regex() refuses them
+        Some(Field::Port) => Vec::new(),
+        Some(Field::Custom(name)) => host
+            .fields
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(key, value)| ("fields", Some(key.as_str()), value.as_str()))
+            .collect(),
+    }
+}
+
+// Character ranges of every regex match, the regex reports byte offsets
+fn regex_ranges(regex: &Regex, text: &str) -> Vec<(usize, usize)> {
+    let char_index = |byte: usize| text[..byte].chars().count();
+    regex
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .map(|m| (char_index(m.start()), char_index(m.end())))
+        .collect()
+}
+
+// Parse a search query. An empty query gives None, which matches every host.
+// `custom_fields` are the lowercase names of the custom fields in use.
+pub fn parse(input: &str, custom_fields: &HashSet<String>) -> Result<Option<Query>, String> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parser = Parser { tokens, position: 0, custom_fields };
+    let query = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(Some(query)),
+        Some(_) => Err("')' without a matching '('".to_string()),
+    }
+}
+
+// Where a host matched, so the UI can highlight it without matching again
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Highlight {
+    field: String,
+    // The tag, alias or custom field name, for fields holding several values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    // [start, end) offsets in UTF-16 code units, so they slice a JavaScript
+    // string directly
+    ranges: Vec<(usize, usize)>,
+}
+
+// A match before merging: field, key, the text matched and character ranges
+type RawHighlight<'a> = (&'static str, Option<&'a str>, &'a str, Vec<(usize, usize)>);
+
+fn push_highlight<'a>(
+    out: &mut Vec<RawHighlight<'a>>,
+    field: &'static str,
+    key: Option<&'a str>,
+    text: &'a str,
+    ranges: Vec<(usize, usize)>,
+) {
+    if !ranges.is_empty() {
+        out.push((field, key, text, ranges));
+    }
+}
+
+fn to_utf16(text: &str, ranges: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut offsets = vec![0];
+    for c in text.chars() {
+        offsets.push(offsets[offsets.len() - 1] + c.len_utf16());
+    }
+    ranges.iter().map(|&(start, end)| (offsets[start], offsets[end])).collect()
+}
+
+fn contains(text: &str, value: &str) -> bool {
+    text.to_lowercase().contains(&value.to_lowercase())
+}
+
+fn field_matches(host: &Host, field: &Field, value: &str) -> bool {
+    match field {
+        Field::Hostname => contains(&host.hostname, value),
+        Field::Alias => host.aliases.iter().any(|alias| contains(alias, value)),
+        Field::Description => contains(&host.description, value),
+        Field::Group => contains(&host.group, value),
+        // Tags and ports are compared whole, tag:prod shouldn't find "preprod"
+        Field::Tag => host.tags.iter().any(|tag| tag.eq_ignore_ascii_case(value)),
+        Field::Os => contains(&host.os, value),
+        Field::Port => host.port.unwrap_or(3389).to_string() == value,
+        Field::Gateway => contains(&host.gateway, value),
+        Field::Custom(name) => host
+            .fields
+            .iter()
+            .any(|(key, field_value)| key.eq_ignore_ascii_case(name) && contains(field_value, value)),
+    }
+}
+
+impl Query {
+    // Score the host against the query, None when it doesn't match. Only
+    // free text contributes to the score, field terms just filter.
+    pub fn score(&self, host: &Host) -> Option<i64> {
+        self.score_with(host, true)
+    }
+
+    fn score_with(&self, host: &Host, fuzzy: bool) -> Option<i64> {
+        match self {
+            Query::Text { value, phrase: false } if fuzzy => search::score_text(host, value),
+            Query::Text { value, .. } => {
+                text_targets(host).iter().any(|(_, _, text)| contains(text, value)).then_some(0)
+            }
+            Query::Field { field, value } => field_matches(host, field, value).then_some(0),
+            Query::Regex { regex, field } => regex_targets(host, field)
+                .iter()
+                .any(|(_, _, text)| regex.is_match(text))
+                .then_some(0),
+            // Fuzzy matching is too loose to exclude by, "-test" would drop
+            // every host with a t, e, s and t in its name
+            Query::Not(inner) => match inner.score_with(host, false) {
+                Some(_) => None,
+                None => Some(0),
+            },
+            Query::And(terms) => terms.iter().map(|term| term.score_with(host, fuzzy)).sum(),
+            Query::Or(alternatives) => alternatives.iter().filter_map(|a| a.score_with(host, fuzzy)).max(),
+        }
+    }
+
+    // Ranges of each field that matched a term, for a host the query matches.
+    // Excluded terms have nothing to show.
+    pub fn highlights(&self, host: &Host) -> Vec<Highlight> {
+        let mut raw = Vec::new();
+        self.collect_highlights(host, &mut raw);
+
+        // Character ranges until the end, when they are merged and converted
+        let mut grouped: Vec<(Highlight, &str)> = Vec::new();
+        for (field, key, text, ranges) in raw {
+            let key = key.map(str::to_string);
+            match grouped.iter_mut().find(|(h, _)| h.field == field && h.key == key) {
+                Some((existing, _)) => existing.ranges.extend(ranges),
+                None => grouped.push((Highlight { field: field.to_string(), key, ranges }, text)),
+            }
+        }
+
+        grouped
+            .into_iter()
+            .map(|(mut highlight, text)| {
+                // Terms can overlap, "sql sql01" matches the same characters twice
+                highlight.ranges.sort_unstable();
+                let mut merged: Vec<(usize, usize)> = Vec::new();
+                for &(start, end) in &highlight.ranges {
+                    match merged.last_mut() {
+                        Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                        _ => merged.push((start, end)),
+                    }
+                }
+                highlight.ranges = to_utf16(text, &merged);
+                highlight
+            })
+            .collect()
+    }
+
+    fn collect_highlights<'a>(&self, host: &'a Host, out: &mut Vec<RawHighlight<'a>>) {
+        match self {
+            Query::Text { value, phrase } => {
+                for (field, key, text) in text_targets(host) {
+                    let ranges = if *phrase {
+                        search::substring_ranges(text, value)
+                    } else {
+                        search::fuzzy_ranges(value, text)
+                    };
+                    push_highlight(out, field, key, text, ranges);
+                }
+            }
+            Query::Field { field, value } => {
+                let (name, text) = match field {
+                    Field::Hostname => ("hostname", &host.hostname),
+                    Field::Description => ("description", &host.description),
+                    Field::Group => ("group", &host.group),
+                    Field::Os => ("os", &host.os),
+                    Field::Gateway => ("gateway", &host.gateway),
+                    Field::Alias => {
+                        for alias in &host.aliases {
+                            push_highlight(out, "aliases", Some(alias), alias, search::substring_ranges(alias, value));
+                        }
+                        return;
+                    }
+                    Field::Tag => {
+                        for tag in host.tags.iter().filter(|tag| tag.eq_ignore_ascii_case(value)) {
+                            push_highlight(out, "tags", Some(tag), tag, vec![(0, tag.chars().count())]);
+                        }
+                        return;
+                    }
+                    Field::Custom(name) => {
+                        for (key, text) in host.fields.iter().filter(|(key, _)| key.eq_ignore_ascii_case(name)) {
+                            push_highlight(out, "fields", Some(key), text, search::substring_ranges(text, value));
+                        }
+                        return;
+                    }
+                    // Not text, nothing to highlight
+                    Field::Port => return,
+                };
+                push_highlight(out, name, None, text, search::substring_ranges(text, value));
+            }
+            Query::Regex { regex, field } => {
+                for (name, key, text) in regex_targets(host, field) {
+                    push_highlight(out, name, key, text, regex_ranges(regex, text));
+                }
+            }
+            Query::Not(_) => {}
+            Query::And(terms) => {
+                for term in terms {
+                    term.collect_highlights(host, out);
+                }
+            }
+            Query::Or(alternatives) => {
+                for alternative in alternatives.iter().filter(|a| a.score(host).is_some()) {
+                    alternative.collect_highlights(host, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{host, strings};
+    use std::collections::BTreeMap;
+
+    fn custom_fields() -> HashSet<String> {
+        HashSet::from(["site".to_string()])
+    }
+
+    // Hostnames of the hosts the query matches
+    fn matching<'a>(query: &str, hosts: &'a [Host]) -> Vec<&'a str> {
+        let query = parse(query, &custom_fields()).unwrap().unwrap();
+        hosts.iter().filter(|h| query.score(h).is_some()).map(|h| h.hostname.as_str()).collect()
+    }
+
+    fn hosts() -> Vec<Host> {
+        vec![
+            Host {
+                description: "Domain controller".to_string(),
+                tags: strings(&["prod"]),
+                ..host("lon-dc01")
+            },
+            Host {
+                description: "File server".to_string(),
+                tags: strings(&["test"]),
+                port: Some(3390),
+                fields: BTreeMap::from([("Site".to_string(), "AMS".to_string())]),
+                ..host("ams-fs01")
+            },
+            Host { os: "Windows Server 2019".to_string(), tags: strings(&["prod"]), ..host("lon-sql01") },
+        ]
+    }
+
+    #[test]
+    fn tokenizes_phrases_fields_and_operators() {
+        let tokens = tokenize(r#"-tag:prod desc:"file server" "domain controller" (a OR b) srv01:3390 fe80::1"#).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Not,
+                Token::FieldWord("tag".to_string(), "prod".to_string()),
+                Token::FieldPhrase("desc".to_string(), "file server".to_string()),
+                Token::Phrase("domain controller".to_string()),
+                Token::Open,
+                Token::Word("a".to_string()),
+                Token::Or,
+                Token::Word("b".to_string()),
+                Token::Close,
+                Token::Word("srv01:3390".to_string()),
+                Token::Word("fe80::1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        for query in [r#""domain"#, r#"desc:"file"#, "tag:", "a OR", "OR a", "()", "(a", "a)", "a -", "port:rdp"] {
+            assert!(parse(query, &custom_fields()).is_err(), "{}", query);
+        }
+        assert!(parse("   ", &custom_fields()).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert_eq!(parse("hostnme:lon", &custom_fields()).unwrap_err(), "Unknown field 'hostnme'");
+        assert!(parse("SITE:ams", &custom_fields()).is_ok());
+        assert!(parse("gw:rdgw", &custom_fields()).is_ok());
+    }
+
+    #[test]
+    fn matches_fields_phrases_and_negation() {
+        let hosts = hosts();
+        assert_eq!(matching("tag:prod", &hosts), vec!["lon-dc01", "lon-sql01"]);
+        assert_eq!(matching("tag:prod -os:2019", &hosts), vec!["lon-dc01"]);
+        assert_eq!(matching(r#"desc:"file server""#, &hosts), vec!["ams-fs01"]);
+        assert_eq!(matching(r#""domain controller""#, &hosts), vec!["lon-dc01"]);
+        assert_eq!(matching("site:ams", &hosts), vec!["ams-fs01"]);
+        assert_eq!(matching("port:3390", &hosts), vec!["ams-fs01"]);
+        assert_eq!(matching("port:3389", &hosts), vec!["lon-dc01", "lon-sql01"]);
+    }
+
+    #[test]
+    fn or_binds_looser_than_and() {
+        let hosts = hosts();
+        assert_eq!(matching("tag:test OR os:2019", &hosts), vec!["ams-fs01", "lon-sql01"]);
+        assert_eq!(matching("tag:prod os:2019 OR tag:test", &hosts), vec!["ams-fs01", "lon-sql01"]);
+        assert_eq!(matching("tag:prod (os:2019 OR desc:domain)", &hosts), vec!["lon-dc01", "lon-sql01"]);
+        assert_eq!(matching("-(tag:prod OR site:ams)", &hosts), Vec::<&str>::new());
+    }
+
+    fn highlights(query: &str, host: &Host) -> Vec<Highlight> {
+        parse(query, &custom_fields()).unwrap().unwrap().highlights(host)
+    }
+
+    fn highlight(field: &str, key: Option<&str>, ranges: Vec<(usize, usize)>) -> Highlight {
+        Highlight { field: field.to_string(), key: key.map(str::to_string), ranges }
+    }
+
+    #[test]
+    fn highlights_what_each_term_matched() {
+        let host = Host {
+            description: "SQL server".to_string(),
+            aliases: strings(&["sqlcluster"]),
+            tags: strings(&["prod"]),
+            ..host("lon-sql01")
+        };
+        assert_eq!(
+            highlights("sql tag:PROD -tag:test", &host),
+            vec![
+                highlight("hostname", None, vec![(4, 7)]),
+                highlight("aliases", Some("sqlcluster"), vec![(0, 3)]),
+                highlight("description", None, vec![(0, 3)]),
+                highlight("tags", Some("prod"), vec![(0, 4)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_overlapping_ranges() {
+        assert_eq!(highlights("sql sql01", &host("lon-sql01")), vec![highlight("hostname", None, vec![(4, 9)])]);
+    }
+
+    #[test]
+    fn highlight_offsets_are_utf16() {
+        let host = Host { description: "🚀 Café server".to_string(), ..host("srv01") };
+        // The rocket is two UTF-16 code units
+        assert_eq!(
+            highlights("\"server\"", &host),
+            vec![highlight("description", None, vec![(8, 14)])]
+        );
+    }
+
+    #[test]
+    fn only_matching_alternatives_are_highlighted() {
+        let host = Host { os: "Windows Server 2019".to_string(), ..host("srv01") };
+        assert_eq!(
+            highlights("os:2019 OR os:2022", &host),
+            vec![highlight("os", None, vec![(15, 19)])]
+        );
+    }
+
+    #[test]
+    fn regex_rejects_fields_it_cannot_search() {
+        let custom_fields = HashSet::from(["site".to_string()]);
+        assert!(regex("^lon", Some("hostname"), &custom_fields).is_ok());
+        assert!(regex("^lon", Some("Site"), &custom_fields).is_ok());
+        assert!(regex("^33", Some("port"), &custom_fields).is_err());
+        assert_eq!(regex("^lon", Some("hostnme"), &custom_fields).unwrap_err(), "Unknown field 'hostnme'");
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{validation, Host};
+
+// Display and redirection options that can be set on a folder and inherited
+// by the hosts inside it. Unset options fall back to the parent folder, and
+// finally to the defaults in build_rdp_file.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RdpSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_screen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub desktop_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub desktop_height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_clipboard: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_printers: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_drives: Option<bool>,
+}
+
+impl RdpSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == RdpSettings::default()
+    }
+
+    // Options set in `other` win over the ones set here
+    pub fn overlay(&self, other: &RdpSettings) -> RdpSettings {
+        RdpSettings {
+            full_screen: other.full_screen.or(self.full_screen),
+            desktop_width: other.desktop_width.or(self.desktop_width),
+            desktop_height: other.desktop_height.or(self.desktop_height),
+            redirect_clipboard: other.redirect_clipboard.or(self.redirect_clipboard),
+            redirect_printers: other.redirect_printers.or(self.redirect_printers),
+            redirect_drives: other.redirect_drives.or(self.redirect_drives),
+        }
+    }
+}
+
+// Build the contents of an .rdp file for the host. Used both when launching
+// mstsc and when exporting .rdp files, so both always connect the same way.
+pub fn build_rdp_file(host: &Host, settings: &RdpSettings, username: &str) -> String {
+    let full_address = validation::format_address(&host.hostname, host.port);
+
+    // gatewayusagemethod 1 always uses the gateway, 4 detects it automatically.
+    // With a gateway set, gatewayprofileusagemethod 1 makes mstsc use it rather
+    // than the user's default gateway settings.
+    let (gateway_usage, gateway_profile_usage) = if host.gateway.is_empty() { (4, 0) } else { (1, 1) };
+
+    let flag = |value: Option<bool>, default: bool| if value.unwrap_or(default) { 1 } else { 0 };
+    // screen mode id 2 is full screen, 1 is windowed
+    let screen_mode = if settings.full_screen.unwrap_or(true) { 2 } else { 1 };
+    let drives = if settings.redirect_drives.unwrap_or(false) { "*" } else { "" };
+
+    format!(
+        "screen mode id:i:{}\r\n\
+         desktopwidth:i:{}\r\n\
+         desktopheight:i:{}\r\n\
+         session bpp:i:32\r\n\
+         full address:s:{}\r\n\
+         compression:i:1\r\n\
+         keyboardhook:i:2\r\n\
+         audiocapturemode:i:1\r\n\
+         videoplaybackmode:i:1\r\n\
+         connection type:i:2\r\n\
+         networkautodetect:i:1\r\n\
+         bandwidthautodetect:i:1\r\n\
+         enableworkspacereconnect:i:1\r\n\
+         disable wallpaper:i:0\r\n\
+         allow desktop composition:i:0\r\n\
+         allow font smoothing:i:0\r\n\
+         disable full window drag:i:1\r\n\
+         disable menu anims:i:1\r\n\
+         disable themes:i:0\r\n\
+         disable cursor setting:i:0\r\n\
+         bitmapcachepersistenable:i:1\r\n\
+         audiomode:i:0\r\n\
+         redirectprinters:i:{}\r\n\
+         redirectcomports:i:0\r\n\
+         redirectsmartcards:i:1\r\n\
+         redirectclipboard:i:{}\r\n\
+         drivestoredirect:s:{}\r\n\
+         redirectposdevices:i:0\r\n\
+         autoreconnection enabled:i:1\r\n\
+         authentication level:i:2\r\n\
+         prompt for credentials:i:0\r\n\
+         negotiate security layer:i:1\r\n\
+         remoteapplicationmode:i:0\r\n\
+         alternate shell:s:\r\n\
+         shell working directory:s:\r\n\
+         gatewayhostname:s:{}\r\n\
+         gatewayusagemethod:i:{}\r\n\
+         gatewaycredentialssource:i:4\r\n\
+         gatewayprofileusagemethod:i:{}\r\n\
+         promptcredentialonce:i:1\r\n\
+         use redirection server name:i:0\r\n\
+         rdgiskdcproxy:i:0\r\n\
+         kdcproxyname:s:\r\n\
+         username:s:{}\r\n\
+         domain:s:\r\n\
+         enablecredsspsupport:i:1\r\n\
+         public mode:i:0\r\n\
+         cert ignore:i:1",
+        screen_mode,
+        settings.desktop_width.unwrap_or(1920),
+        settings.desktop_height.unwrap_or(1080),
+        full_address,
+        flag(settings.redirect_printers, true),
+        flag(settings.redirect_clipboard, true),
+        drives,
+        host.gateway,
+        gateway_usage,
+        gateway_profile_usage,
+        username
+    )
+}
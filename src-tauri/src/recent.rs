@@ -0,0 +1,126 @@
+use crate::{log_to_file, storage, usage};
+
+const RECENT_FILE: &str = "recent.json";
+
+// Older connections drop off the end of the list
+const MAX_RECENT: usize = 50;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecentConnection {
+    hostname: String,
+    // What mstsc connected to when it wasn't the hostname: an alias or an IP
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    address: String,
+    // Seconds since the epoch
+    connected_at: u64,
+    // Credential profile whose credentials were used, empty for the default ones
+    #[serde(default)]
+    credential_profile: String,
+}
+
+// Most recent first, one entry per host
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct RecentStore {
+    connections: Vec<RecentConnection>,
+}
+
+fn load_store() -> Result<RecentStore, String> {
+    storage::load_json(RECENT_FILE)
+}
+
+fn save_store(store: &RecentStore) -> Result<(), String> {
+    storage::save_json(RECENT_FILE, store)
+}
+
+// Move the host to the top of the list. Like usage, failing to save is logged
+// rather than failing a launch that already happened. A file that can't be
+// read is left alone, clear_recent_connections starts it over.
+pub fn record_connection(hostname: &str, address: &str, credential_profile: &str) {
+    let mut store = match load_store() {
+        Ok(store) => store,
+        Err(e) => {
+            log_to_file(&format!("Failed to load recent connections, not recording {}: {}", hostname, e));
+            return;
+        }
+    };
+
+    push_recent(&mut store.connections, hostname, address, credential_profile, usage::now());
+
+    if let Err(e) = save_store(&store) {
+        log_to_file(&format!("Failed to save recent connections: {}", e));
+    }
+}
+
+fn push_recent(connections: &mut Vec<RecentConnection>, hostname: &str, address: &str, credential_profile: &str, now: u64) {
+    connections.retain(|c| !c.hostname.eq_ignore_ascii_case(hostname));
+    let address = if address.eq_ignore_ascii_case(hostname) { "" } else { address };
+    connections.insert(
+        0,
+        RecentConnection {
+            hostname: hostname.to_string(),
+            address: address.to_string(),
+            connected_at: now,
+            credential_profile: credential_profile.to_string(),
+        },
+    );
+    connections.truncate(MAX_RECENT);
+}
+
+// Hosts connected to, most recent first
+#[tauri::command]
+pub fn get_recent_connections(limit: Option<usize>) -> Result<Vec<RecentConnection>, String> {
+    let mut connections = load_store()?.connections;
+    connections.truncate(limit.unwrap_or(MAX_RECENT));
+    Ok(connections)
+}
+
+#[tauri::command]
+pub fn clear_recent_connections() -> Result<(), String> {
+    save_store(&RecentStore::default())?;
+    log_to_file("Cleared recent connections");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hostnames(connections: &[RecentConnection]) -> Vec<&str> {
+        connections.iter().map(|c| c.hostname.as_str()).collect()
+    }
+
+    #[test]
+    fn moves_a_reconnected_host_to_the_top() {
+        let mut connections = Vec::new();
+        push_recent(&mut connections, "lon-sql01", "", "", 100);
+        push_recent(&mut connections, "ams-web01", "", "", 200);
+        push_recent(&mut connections, "LON-SQL01", "", "admin", 300);
+
+        assert_eq!(hostnames(&connections), ["LON-SQL01", "ams-web01"]);
+        assert_eq!(connections[0].connected_at, 300);
+        assert_eq!(connections[0].credential_profile, "admin");
+    }
+
+    #[test]
+    fn only_keeps_addresses_other_than_the_hostname() {
+        let mut connections = Vec::new();
+        push_recent(&mut connections, "lon-sql01", "LON-SQL01", "", 100);
+        assert_eq!(connections[0].address, "");
+
+        push_recent(&mut connections, "lon-sql01", "10.0.0.5", "", 200);
+        assert_eq!(connections[0].address, "10.0.0.5");
+        assert!(serde_json::to_value(&connections[0]).unwrap().get("address").is_some());
+    }
+
+    #[test]
+    fn drops_the_oldest_connections() {
+        let mut connections = Vec::new();
+        for i in 0..=MAX_RECENT {
+            push_recent(&mut connections, &format!("srv{:02}", i), "", "", i as u64);
+        }
+        assert_eq!(connections.len(), MAX_RECENT);
+        assert_eq!(connections[0].hostname, format!("srv{:02}", MAX_RECENT));
+        assert!(!connections.iter().any(|c| c.hostname == "srv00"));
+    }
+}
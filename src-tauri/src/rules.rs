@@ -0,0 +1,202 @@
+use crate::{get_hosts, history, log_to_file, snapshots, storage, validation, write_hosts, Host};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+const RULES_FILE: &str = "rules.json";
+
+// A naming convention such as LON-PRD-SQL01. The pattern is matched against
+// the hostname, and tags and field values can use its capture groups, e.g.
+// pattern "^(?<site>[A-Z]{3})-(?<env>PRD|TST)-" with fields {"site": "$site"}.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TagRule {
+    name: String,
+    pattern: String,
+    tags: Vec<String>,
+    fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct RulesStore {
+    rules: Vec<TagRule>,
+}
+
+// A rule with its pattern compiled, ready to match hostnames
+pub struct CompiledRule {
+    rule: TagRule,
+    regex: Regex,
+}
+
+fn compile(rules: Vec<TagRule>) -> Result<Vec<CompiledRule>, String> {
+    rules
+        .into_iter()
+        .map(|rule| {
+            // Hostnames are case-insensitive, so the patterns are too
+            let regex = Regex::new(&format!("(?i){}", rule.pattern))
+                .map_err(|e| format!("Invalid pattern in rule '{}': {}", rule.name, e))?;
+            Ok(CompiledRule { rule, regex })
+        })
+        .collect()
+}
+
+// The saved rules, compiled. A broken rules file is logged and ignored so it
+// never blocks saving hosts.
+pub fn load_rules() -> Vec<CompiledRule> {
+    let store: RulesStore = match storage::load_json(RULES_FILE) {
+        Ok(store) => store,
+        Err(e) => {
+            log_to_file(&format!("Failed to load tag rules: {}", e));
+            return Vec::new();
+        }
+    };
+    compile(store.rules).unwrap_or_else(|e| {
+        log_to_file(&format!("Failed to load tag rules: {}", e));
+        Vec::new()
+    })
+}
+
+// Add the tags and fields of every rule matching the hostname. Tags are only
+// ever added; a field set by a rule replaces the value the host had.
+pub fn apply_rules(host: &Host, rules: &[CompiledRule]) -> Host {
+    let mut host = host.clone();
+    for compiled in rules {
+        let Some(captures) = compiled.regex.captures(&host.hostname) else {
+            continue;
+        };
+        let expand = |template: &str| {
+            let mut value = String::new();
+            captures.expand(template, &mut value);
+            value.trim().to_string()
+        };
+
+        for template in &compiled.rule.tags {
+            let tag = expand(template);
+            if !tag.is_empty() && !tag.contains(';') && !host.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                host.tags.push(tag);
+            }
+        }
+        for (key, template) in &compiled.rule.fields {
+            let value = expand(template);
+            if !value.is_empty() && !value.contains(';') {
+                host.fields.insert(key.trim().to_string(), value);
+            }
+        }
+    }
+    host
+}
+
+// The host with the rules applied. Only the tags and fields a rule changed are
+// checked and taken over, so hosts the rules don't touch stay exactly as they
+// were. Rules can produce values the host can't keep, that is an error.
+fn retag(host: &Host, rules: &[CompiledRule]) -> Result<Host, String> {
+    let tagged = apply_rules(host, rules);
+    if tagged.tags == host.tags && tagged.fields == host.fields {
+        return Ok(host.clone());
+    }
+    let normalized = validation::normalize_host(&tagged).map_err(|errors| validation::describe(&errors))?;
+    Ok(Host {
+        tags: normalized.tags,
+        fields: normalized.fields,
+        ..host.clone()
+    })
+}
+
+#[tauri::command]
+pub fn get_tag_rules() -> Result<Vec<TagRule>, String> {
+    let store: RulesStore = storage::load_json(RULES_FILE)?;
+    Ok(store.rules)
+}
+
+#[tauri::command]
+pub fn save_tag_rules(rules: Vec<TagRule>) -> Result<(), String> {
+    for rule in &rules {
+        if rule.pattern.trim().is_empty() {
+            return Err(format!("Rule '{}' needs a pattern", rule.name));
+        }
+        if let Some(key) = rule.fields.keys().find(|k| k.trim().is_empty() || k.contains(['=', ';'])) {
+            return Err(format!("Invalid field name '{}' in rule '{}'", key, rule.name));
+        }
+    }
+    // Check every pattern compiles before anything is saved
+    compile(rules.clone())?;
+
+    storage::save_json(RULES_FILE, &RulesStore { rules })?;
+    log_to_file("Saved tag rules");
+    Ok(())
+}
+
+// Re-apply the rules to the whole inventory, e.g. after changing them.
+// Returns how many hosts changed.
+#[tauri::command]
+pub fn apply_tag_rules() -> Result<usize, String> {
+    let rules = load_rules();
+    let before = get_hosts()?;
+
+    let mut after = Vec::with_capacity(before.len());
+    for host in &before {
+        match retag(host, &rules) {
+            Ok(tagged) => after.push(tagged),
+            Err(e) => {
+                log_to_file(&format!("Tag rules not applied to {}: {}", host.hostname, e));
+                after.push(host.clone());
+            }
+        }
+    }
+
+    let changed = before.iter().zip(&after).filter(|(old, new)| old != new).count();
+    if changed > 0 {
+        snapshots::create_snapshot("tag-rules")?;
+        write_hosts(&after)?;
+        history::record_all("auto-tagged", &before, &after);
+    }
+
+    log_to_file(&format!("Applied tag rules, {} host(s) changed", changed));
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{host, strings};
+
+    fn rules() -> Vec<CompiledRule> {
+        compile(vec![TagRule {
+            name: "site".to_string(),
+            pattern: "^(?<site>[a-z]{3})-(?<env>prd|tst)-".to_string(),
+            tags: vec!["$env".to_string()],
+            fields: BTreeMap::from([("site".to_string(), "$site".to_string())]),
+        }])
+        .unwrap()
+    }
+
+    #[test]
+    fn adds_tags_and_fields_from_the_hostname() {
+        let tagged = retag(&Host { tags: strings(&["sql"]), ..host("LON-PRD-SQL01") }, &rules()).unwrap();
+        assert_eq!(tagged.tags, strings(&["sql", "PRD"]));
+        assert_eq!(tagged.fields, BTreeMap::from([("site".to_string(), "LON".to_string())]));
+    }
+
+    #[test]
+    fn leaves_hosts_the_rules_dont_change_alone() {
+        // Not normalized, so this would change if it were
+        let untouched = Host { description: " File server ".to_string(), ..host("srv01:3390") };
+        assert_eq!(retag(&untouched, &rules()).unwrap(), untouched);
+
+        let tagged = Host {
+            description: " SQL ".to_string(),
+            tags: strings(&["prd"]),
+            fields: BTreeMap::from([("site".to_string(), "lon".to_string())]),
+            ..host("lon-prd-sql01")
+        };
+        assert_eq!(retag(&tagged, &rules()).unwrap(), tagged);
+    }
+
+    #[test]
+    fn only_takes_over_what_a_rule_changed() {
+        let host = Host { description: " SQL ".to_string(), ..host("lon-prd-sql01") };
+        let tagged = retag(&host, &rules()).unwrap();
+        assert_eq!(tagged.description, " SQL ");
+        assert_eq!(tagged.tags, strings(&["prd"]));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{exporters, launch_rdp, log_to_file, query, search_index, storage, Host};
+use std::collections::HashSet;
+
+const SAVED_SEARCHES_FILE: &str = "saved_searches.json";
+
+// Launching a smart group opens a window per host, more than this is almost
+// certainly a query that matches more than intended
+const MAX_LAUNCH: usize = 20;
+
+// A named query, evaluated against the inventory every time it is used so its
+// hosts are always current
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SavedSearch {
+    name: String,
+    query: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SavedSearchInfo {
+    #[serde(flatten)]
+    search: SavedSearch,
+    // Number of hosts the query matches right now
+    hosts: usize,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct SavedSearchStore {
+    searches: Vec<SavedSearch>,
+}
+
+fn load_store() -> Result<SavedSearchStore, String> {
+    storage::load_json(SAVED_SEARCHES_FILE)
+}
+
+fn save_store(store: &SavedSearchStore) -> Result<(), String> {
+    storage::save_json(SAVED_SEARCHES_FILE, store)
+}
+
+// `custom_fields` are the lowercase custom field names the query may use
+fn validate(name: &str, query: &str, custom_fields: &HashSet<String>) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Saved search name cannot be empty".to_string());
+    }
+    match query::parse(query, custom_fields) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err("Saved search query cannot be empty".to_string()),
+        Err(e) => Err(format!("Invalid query: {}", e)),
+    }
+}
+
+fn find_search(name: &str) -> Result<SavedSearch, String> {
+    load_store()?
+        .searches
+        .into_iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name))
+        .ok_or(format!("Saved search not found: {}", name))
+}
+
+#[tauri::command]
+pub fn list_saved_searches() -> Result<Vec<SavedSearchInfo>, String> {
+    let store = load_store()?;
+    store
+        .searches
+        .into_iter()
+        .map(|search| {
+            let hosts = search_index::matching_hosts(&search.query)?.len();
+            Ok(SavedSearchInfo { search, hosts })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn create_saved_search(name: String, query: String) -> Result<(), String> {
+    validate(&name, &query, &search_index::custom_field_names()?)?;
+    let mut store = load_store()?;
+    if store.searches.iter().any(|s| s.name.eq_ignore_ascii_case(name.trim())) {
+        return Err(format!("A saved search named {} already exists", name.trim()));
+    }
+
+    store.searches.push(SavedSearch { name: name.trim().to_string(), query: query.trim().to_string() });
+    save_store(&store)?;
+    log_to_file(&format!("Saved search {}: {}", name.trim(), query.trim()));
+    Ok(())
+}
+
+// Rename a saved search, change its query, or both
+#[tauri::command]
+pub fn update_saved_search(name: String, new_name: Option<String>, query: Option<String>) -> Result<(), String> {
+    let mut store = load_store()?;
+    let index = store
+        .searches
+        .iter()
+        .position(|s| s.name.eq_ignore_ascii_case(&name))
+        .ok_or(format!("Saved search not found: {}", name))?;
+
+    let new_name = new_name.map(|n| n.trim().to_string()).unwrap_or_else(|| store.searches[index].name.clone());
+    let query = query.map(|q| q.trim().to_string()).unwrap_or_else(|| store.searches[index].query.clone());
+    validate(&new_name, &query, &search_index::custom_field_names()?)?;
+    if store
+        .searches
+        .iter()
+        .enumerate()
+        .any(|(i, s)| i != index && s.name.eq_ignore_ascii_case(&new_name))
+    {
+        return Err(format!("A saved search named {} already exists", new_name));
+    }
+
+    store.searches[index] = SavedSearch { name: new_name, query };
+    save_store(&store)?;
+    log_to_file(&format!("Updated saved search {}", name));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_saved_search(name: String) -> Result<(), String> {
+    let mut store = load_store()?;
+    let before = store.searches.len();
+    store.searches.retain(|s| !s.name.eq_ignore_ascii_case(&name));
+    if store.searches.len() == before {
+        return Err(format!("Saved search not found: {}", name));
+    }
+
+    save_store(&store)?;
+    log_to_file(&format!("Deleted saved search {}", name));
+    Ok(())
+}
+
+// The hosts a saved search matches right now
+#[tauri::command]
+pub fn get_saved_search_hosts(name: String) -> Result<Vec<Host>, String> {
+    let search = find_search(&name)?;
+    search_index::matching_hosts(&search.query)
+}
+
+// Connect to every host of a saved search. Hosts that fail to launch don't
+// stop the rest; they are listed in the error afterwards.
+#[tauri::command]
+pub async fn launch_saved_search(name: String) -> Result<usize, String> {
+    let hosts = get_saved_search_hosts(name.clone())?;
+    if hosts.len() > MAX_LAUNCH {
+        return Err(format!(
+            "{} matches {} hosts, launching is limited to {} at a time",
+            name,
+            hosts.len(),
+            MAX_LAUNCH
+        ));
+    }
+
+    let mut failures = Vec::new();
+    for host in &hosts {
+        if let Err(e) = launch_rdp(host.clone(), None).await {
+            failures.push(format!("{}: {}", host.hostname, e));
+        }
+    }
+    log_to_file(&format!("Launched saved search {}: {} host(s), {} failed", name, hosts.len(), failures.len()));
+
+    if !failures.is_empty() {
+        return Err(format!("Failed to launch {} of {} host(s):\n{}", failures.len(), hosts.len(), failures.join("\n")));
+    }
+    Ok(hosts.len())
+}
+
+// Export the hosts of a saved search, in any format export_hosts supports
+#[tauri::command]
+pub async fn export_saved_search(
+    name: String,
+    path: String,
+    format: String,
+    include_credentials: bool,
+) -> Result<usize, String> {
+    let hostnames = get_saved_search_hosts(name)?
+        .into_iter()
+        .map(|host| host.hostname)
+        .collect();
+    exporters::export_hosts(path, format, Some(hostnames), include_credentials).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_searches_need_a_name_and_a_valid_query() {
+        let custom_fields = HashSet::from(["site".to_string()]);
+        assert!(validate("London SQL", "site:LON tag:sql", &custom_fields).is_ok());
+        assert!(validate("  ", "tag:sql", &custom_fields).is_err());
+        assert!(validate("Empty", "  ", &custom_fields).is_err());
+        assert!(validate("Broken", "(tag:sql", &custom_fields).is_err());
+        assert_eq!(
+            validate("Typo", "sitee:LON", &custom_fields).unwrap_err(),
+            "Invalid query: Unknown field 'sitee'"
+        );
+    }
+}
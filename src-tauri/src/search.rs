@@ -0,0 +1,244 @@
+use crate::Host;
+
+// A hostname or alias match counts this many times a description match
+const HOSTNAME_WEIGHT: i64 = 3;
+
+const MATCH_SCORE: i64 = 2;
+const CONSECUTIVE_BONUS: i64 = 6;
+// Match starting a word: after '-', '.', '_', a space or a letter/digit switch
+const BOUNDARY_BONUS: i64 = 8;
+const PREFIX_BONUS: i64 = 20;
+// Per character skipped between two matched characters, capped per gap
+const GAP_PENALTY: i64 = 1;
+const MAX_GAP_PENALTY: i64 = 6;
+const TYPO_PENALTY: i64 = 12;
+
+// Lowercase one character at a time, so positions in the result line up with
+// the characters of the original text
+pub fn fold(text: &str) -> Vec<char> {
+    text.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect()
+}
+
+fn is_boundary(text: &[char], index: usize) -> bool {
+    if index == 0 {
+        return true;
+    }
+    let (prev, current) = (text[index - 1], text[index]);
+    !prev.is_alphanumeric() || (prev.is_alphabetic() && current.is_ascii_digit())
+}
+
+// Score the query as a subsequence of the text starting at `start`, taking the
+// earliest match for each following character
+fn subsequence_from(query: &[char], text: &[char], start: usize) -> Option<i64> {
+    let mut score = 0;
+    let mut position = start;
+    let mut previous: Option<usize> = None;
+
+    for &q in query {
+        let found = (position..text.len()).find(|&i| text[i] == q)?;
+        score += MATCH_SCORE;
+        if is_boundary(text, found) {
+            score += BOUNDARY_BONUS;
+        }
+        match previous {
+            Some(prev) if found == prev + 1 => score += CONSECUTIVE_BONUS,
+            Some(prev) => score -= ((found - prev - 1) as i64 * GAP_PENALTY).min(MAX_GAP_PENALTY),
+            None if found == 0 => score += PREFIX_BONUS,
+            None => {}
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+// Best subsequence score over every place the first query character occurs,
+// and where that match starts
+fn best_subsequence(query: &[char], text: &[char]) -> Option<(i64, usize)> {
+    let first = *query.first()?;
+    (0..text.len())
+        .filter(|&i| text[i] == first)
+        .filter_map(|i| subsequence_from(query, text, i).map(|score| (score, i)))
+        .max_by_key(|(score, i)| (*score, std::cmp::Reverse(*i)))
+}
+
+// Fewest edits turning the query into any substring of the text (Sellers'
+// algorithm), for queries with a typo that no longer form a subsequence
+fn substring_distance(query: &[char], text: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=query.len()).collect();
+    let mut best = previous[query.len()];
+
+    for &t in text {
+        let mut current = vec![0; query.len() + 1];
+        for (j, &q) in query.iter().enumerate() {
+            let substitution = previous[j] + usize::from(q != t);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        best = best.min(current[query.len()]);
+        previous = current;
+    }
+    best
+}
+
+// Short queries have to match exactly, longer ones may contain a typo or two
+pub fn allowed_typos(query_len: usize) -> usize {
+    match query_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, &x) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &y) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + usize::from(x != y)).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Score a single folded term against a field, None when it doesn't match
+fn fuzzy_score(term: &[char], field: &str) -> Option<i64> {
+    let text = fold(field);
+    if let Some((score, _)) = best_subsequence(term, &text) {
+        return Some(score);
+    }
+
+    let typos = substring_distance(term, &text);
+    (typos <= allowed_typos(term.len())).then(|| term.len() as i64 * MATCH_SCORE - typos as i64 * TYPO_PENALTY)
+}
+
+// Lowest score a matching term of this length can get, either scattered
+// across the text or with as many typos as allowed
+fn min_score(term_len: usize) -> i64 {
+    let len = term_len as i64;
+    let scattered = len * MATCH_SCORE - (len - 1).max(0) * MAX_GAP_PENALTY;
+    let typos = len * MATCH_SCORE - allowed_typos(term_len) as i64 * TYPO_PENALTY;
+    scattered.min(typos)
+}
+
+// Score a free-text term against the hostname, the aliases and the
+// description, higher is better. None when it matches none of them.
+pub fn score_text(host: &Host, term: &str) -> Option<i64> {
+    let term = fold(term);
+    // Scores can be negative, shift them up first so weighting a poor
+    // hostname match doesn't sink it below the same match in the description
+    let floor = min_score(term.len());
+    let hostname = std::iter::once(&host.hostname)
+        .chain(&host.aliases)
+        .filter_map(|name| fuzzy_score(&term, name))
+        .max()
+        .map(|s| (s - floor) * HOSTNAME_WEIGHT);
+    let description = fuzzy_score(&term, &host.description).map(|s| s - floor);
+    hostname.max(description)
+}
+
+// Join neighbouring positions into [start, end) ranges
+fn to_ranges(positions: &[usize]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &position in positions {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == position => *end += 1,
+            _ => ranges.push((position, position + 1)),
+        }
+    }
+    ranges
+}
+
+// Character ranges of the field a fuzzy term matched, the same match
+// fuzzy_score scored. Empty when the term doesn't match the field.
+pub fn fuzzy_ranges(term: &str, field: &str) -> Vec<(usize, usize)> {
+    let term = fold(term);
+    let text = fold(field);
+
+    if let Some((_, start)) = best_subsequence(&term, &text) {
+        let mut positions = Vec::with_capacity(term.len());
+        let mut next = start;
+        for &c in &term {
+            match (next..text.len()).find(|&i| text[i] == c) {
+                Some(found) => {
+                    positions.push(found);
+                    next = found + 1;
+                }
+                None => return Vec::new(),
+            }
+        }
+        return to_ranges(&positions);
+    }
+
+    // A typo: the closest substring within the allowed number of edits.
+    // Only done for the results being shown, so trying every one is fine.
+    let allowed = allowed_typos(term.len());
+    let mut best: Option<(usize, usize, usize)> = None;
+    for end in 1..=text.len() {
+        for start in end.saturating_sub(term.len() + allowed)..end {
+            let distance = edit_distance(&term, &text[start..end]);
+            if distance <= allowed && best.is_none_or(|(best_distance, _, _)| distance < best_distance) {
+                best = Some((distance, start, end));
+            }
+        }
+    }
+    best.map(|(_, start, end)| vec![(start, end)]).unwrap_or_default()
+}
+
+// Character ranges of every occurrence of the value in the text, ignoring case
+pub fn substring_ranges(text: &str, value: &str) -> Vec<(usize, usize)> {
+    let text = fold(text);
+    let value = fold(value);
+    if value.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start + value.len() <= text.len() {
+        if text[start..start + value.len()] == value[..] {
+            ranges.push((start, start + value.len()));
+            start += value.len();
+        } else {
+            start += 1;
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host;
+
+    #[test]
+    fn hostname_typo_ranks_above_description_typo() {
+        // "abcd" is one typo away from "abxd" in both
+        let in_hostname = score_text(&host("abxd-01"), "abcd").unwrap();
+        let in_description = score_text(&Host { description: "abxd".to_string(), ..host("srv01") }, "abcd").unwrap();
+        assert!(in_hostname > in_description, "{} <= {}", in_hostname, in_description);
+    }
+
+    #[test]
+    fn hostname_scattered_match_ranks_above_description() {
+        let in_hostname = score_text(&host("s-x-q-x-l"), "sql").unwrap();
+        let in_description = score_text(&Host { description: "s-x-q-x-l".to_string(), ..host("web01") }, "sql").unwrap();
+        assert!(in_hostname > in_description, "{} <= {}", in_hostname, in_description);
+    }
+
+    #[test]
+    fn aliases_count_as_much_as_the_hostname() {
+        let by_alias = score_text(&Host { aliases: vec!["sqlcluster".to_string()], ..host("srv01") }, "sqlcl").unwrap();
+        let by_hostname = score_text(&host("sqlcluster"), "sqlcl").unwrap();
+        assert_eq!(by_alias, by_hostname);
+        assert!(score_text(&Host { aliases: vec!["web01".to_string()], ..host("srv01") }, "sqlcl").is_none());
+    }
+
+    #[test]
+    fn scores_are_never_negative() {
+        for (text, term) in [("abxd", "abcd"), ("axxxxbxxxxcxxxxd", "abcd"), ("lon-prd-sql01", "lnsql")] {
+            let score = score_text(&host(text), term).unwrap();
+            assert!(score >= 0, "{} for {} in {}", score, term, text);
+        }
+    }
+}
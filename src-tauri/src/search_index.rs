@@ -0,0 +1,425 @@
+use crate::query::{Field, Highlight, Query};
+use crate::usage::{self, HostUsage};
+use crate::{get_hosts, log_to_file, query, search, Host};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+// Built on the first search and kept up to date as the inventory changes, so
+// a keystroke doesn't re-read and re-scan the whole inventory
+static INDEX: Mutex<Option<SearchIndex>> = Mutex::new(None);
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    host: Host,
+    // Seconds since the epoch, None if never connected to
+    last_connected: Option<u64>,
+    // Why the host matched, empty when the query was
+    matches: Vec<Highlight>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchPage {
+    // Number of matching hosts, the page holds at most `limit` of them
+    total: usize,
+    results: Vec<SearchResult>,
+}
+
+struct Doc {
+    host: Host,
+    // Position in the inventory, ties and empty searches keep this order
+    position: usize,
+}
+
+// Hosts live in slots that stay put while others come and go. Postings are
+// sorted lists of slots.
+#[derive(Default)]
+struct SearchIndex {
+    docs: Vec<Option<Doc>>,
+    by_name: HashMap<String, u32>,
+    // Characters of the hostname, aliases and description, for fuzzy terms
+    chars: HashMap<char, Vec<u32>>,
+    // Trigrams of every text field, for phrases and field terms
+    trigrams: HashMap<[char; 3], Vec<u32>>,
+    free_slots: usize,
+}
+
+fn fuzzy_chars(host: &Host) -> HashSet<char> {
+    std::iter::once(&host.hostname)
+        .chain(&host.aliases)
+        .chain(std::iter::once(&host.description))
+        .flat_map(|text| text.to_lowercase().chars().collect::<Vec<_>>())
+        .collect()
+}
+
+fn trigrams_of(text: &str) -> impl Iterator<Item = [char; 3]> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    (0..chars.len().saturating_sub(2))
+        .map(move |i| [chars[i], chars[i + 1], chars[i + 2]])
+}
+
+fn host_trigrams(host: &Host) -> HashSet<[char; 3]> {
+    [&host.hostname, &host.description, &host.group, &host.os, &host.gateway]
+        .into_iter()
+        .chain(&host.aliases)
+        .chain(&host.tags)
+        .chain(host.fields.values())
+        .flat_map(|text| trigrams_of(text))
+        .collect()
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let b: HashSet<u32> = b.iter().copied().collect();
+    a.iter().copied().filter(|slot| b.contains(slot)).collect()
+}
+
+impl SearchIndex {
+    fn build(hosts: &[Host]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (position, host) in hosts.iter().enumerate() {
+            index.insert(host.clone(), position);
+        }
+        index
+    }
+
+    fn insert(&mut self, host: Host, position: usize) {
+        // New slots are always the highest, so postings stay sorted by pushing
+        let slot = self.docs.len() as u32;
+        for c in fuzzy_chars(&host) {
+            self.chars.entry(c).or_default().push(slot);
+        }
+        for trigram in host_trigrams(&host) {
+            self.trigrams.entry(trigram).or_default().push(slot);
+        }
+        self.by_name.insert(host.hostname.to_lowercase(), slot);
+        self.docs.push(Some(Doc { host, position }));
+    }
+
+    fn remove(&mut self, hostname: &str) {
+        let Some(slot) = self.by_name.remove(&hostname.to_lowercase()) else {
+            return;
+        };
+        let Some(doc) = self.docs[slot as usize].take() else {
+            return;
+        };
+        let remove_from = |posting: &mut Vec<u32>| {
+            if let Ok(i) = posting.binary_search(&slot) {
+                posting.remove(i);
+            }
+        };
+        for c in fuzzy_chars(&doc.host) {
+            self.chars.entry(c).and_modify(remove_from);
+        }
+        for trigram in host_trigrams(&doc.host) {
+            self.trigrams.entry(trigram).and_modify(remove_from);
+        }
+        self.free_slots += 1;
+    }
+
+    // Bring the index in line with the inventory, only touching hosts that
+    // were added, removed or changed
+    fn update(&mut self, hosts: &[Host]) {
+        let names: HashSet<String> = hosts.iter().map(|h| h.hostname.to_lowercase()).collect();
+        let gone: Vec<String> = self.by_name.keys().filter(|name| !names.contains(*name)).cloned().collect();
+        for name in gone {
+            self.remove(&name);
+        }
+
+        for (position, host) in hosts.iter().enumerate() {
+            let slot = self.by_name.get(&host.hostname.to_lowercase()).copied();
+            match slot.and_then(|slot| self.docs[slot as usize].as_mut()) {
+                Some(doc) if doc.host == *host => doc.position = position,
+                Some(_) => {
+                    self.remove(&host.hostname);
+                    self.insert(host.clone(), position);
+                }
+                None => self.insert(host.clone(), position),
+            }
+        }
+
+        // Removed hosts leave empty slots behind, start over once they pile up
+        if self.free_slots > 1000 && self.free_slots > self.docs.len() / 2 {
+            *self = SearchIndex::build(hosts);
+        }
+    }
+
+    // Hosts that contain every character of the term, give or take the typos
+    // the fuzzy match allows
+    fn fuzzy_candidates(&self, term: &str) -> Option<Vec<u32>> {
+        let term: Vec<char> = term.to_lowercase().chars().collect();
+        let typos = search::allowed_typos(term.len());
+        let term: HashSet<char> = term.into_iter().collect();
+        // Each typo can cost at most one of the characters
+        let needed = term.len().saturating_sub(typos);
+        if needed == 0 {
+            return None;
+        }
+
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for posting in term.iter().filter_map(|c| self.chars.get(c)) {
+            for &slot in posting {
+                *counts.entry(slot).or_default() += 1;
+            }
+        }
+        let mut slots: Vec<u32> = counts.into_iter().filter(|(_, n)| *n >= needed).map(|(slot, _)| slot).collect();
+        slots.sort_unstable();
+        Some(slots)
+    }
+
+    // Hosts with every trigram of the value in one of their fields. Values
+    // too short to have a trigram can't be narrowed down.
+    fn substring_candidates(&self, value: &str) -> Option<Vec<u32>> {
+        let mut trigrams = trigrams_of(value).peekable();
+        trigrams.peek()?;
+
+        let mut slots: Option<Vec<u32>> = None;
+        for trigram in trigrams {
+            let posting = self.trigrams.get(&trigram).map(Vec::as_slice).unwrap_or(&[]);
+            slots = Some(match slots {
+                None => posting.to_vec(),
+                Some(slots) => intersect(&slots, posting),
+            });
+        }
+        slots
+    }
+
+    // Slots that can possibly match, None meaning all of them. Every
+    // candidate is still checked against the query itself.
+    fn candidates(&self, query: &Query) -> Option<Vec<u32>> {
+        match query {
+            Query::Text { value, phrase: false } => self.fuzzy_candidates(value),
+            Query::Text { value, phrase: true } => self.substring_candidates(value),
+            Query::Field { field: Field::Port, .. } => None,
+            Query::Field { value, .. } => self.substring_candidates(value),
+            Query::Regex { .. } => None,
+            Query::Not(_) => None,
+            Query::And(terms) => terms
+                .iter()
+                .filter_map(|term| self.candidates(term))
+                .reduce(|a, b| intersect(&a, &b)),
+            Query::Or(alternatives) => {
+                let mut slots = Vec::new();
+                for alternative in alternatives {
+                    slots.extend(self.candidates(alternative)?);
+                }
+                slots.sort_unstable();
+                slots.dedup();
+                Some(slots)
+            }
+        }
+    }
+
+    // Matching hosts, best match first, then in inventory order. Hosts
+    // connected to often and recently get a boost.
+    fn search<'a>(&'a self, query: Option<&Query>, usage: &'a HashMap<String, HostUsage>) -> Vec<(&'a Host, Option<&'a HostUsage>)> {
+        let docs: Vec<&Doc> = match query.and_then(|q| self.candidates(q)) {
+            Some(slots) => slots.iter().filter_map(|&slot| self.docs[slot as usize].as_ref()).collect(),
+            None => self.docs.iter().flatten().collect(),
+        };
+
+        let now = usage::now();
+        let mut scored: Vec<(i64, &Doc, Option<&HostUsage>)> = docs
+            .into_iter()
+            .filter_map(|doc| {
+                let score = match query {
+                    Some(query) => query.score(&doc.host)?,
+                    None => 0,
+                };
+                let usage = usage.get(&doc.host.hostname.to_lowercase());
+                let bonus = usage.map_or(0, |usage| usage.search_bonus(now));
+                Some((score + bonus, doc, usage))
+            })
+            .collect();
+        scored.sort_by_key(|(score, doc, _)| (std::cmp::Reverse(*score), doc.position));
+        scored.into_iter().map(|(_, doc, usage)| (&doc.host, usage)).collect()
+    }
+}
+
+// Keep the index in line with a new version of the inventory. Called whenever
+// the inventory is written or reloaded; before the first search there is
+// nothing to update.
+pub fn update(hosts: &[Host]) {
+    match INDEX.lock() {
+        Ok(mut index) => {
+            if let Some(index) = index.as_mut() {
+                index.update(hosts);
+            }
+        }
+        Err(_) => log_to_file("Failed to acquire search index lock"),
+    }
+}
+
+// Drop the index, the next search builds it from the inventory file again
+pub fn reset() {
+    match INDEX.lock() {
+        Ok(mut index) => *index = None,
+        Err(_) => log_to_file("Failed to acquire search index lock"),
+    }
+}
+
+// One page of the hosts matching a parsed query, best match first. Without a
+// query every host matches, without a limit every match is returned.
+pub fn search(query: Option<Query>, limit: Option<usize>, offset: usize) -> Result<SearchPage, String> {
+    with_index(|index| {
+        usage::with_usage(|usage| {
+            let matches = index.search(query.as_ref(), usage);
+            let results = matches
+                .iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|(host, usage)| SearchResult {
+                    host: (*host).clone(),
+                    last_connected: usage.map(|usage| usage.last_connected),
+                    matches: query.as_ref().map(|q| q.highlights(host)).unwrap_or_default(),
+                })
+                .collect();
+            SearchPage { total: matches.len(), results }
+        })
+    })
+}
+
+// Every host matching the query, best match first, for acting on a search as
+// a whole rather than showing it
+pub fn matching_hosts(query: &str) -> Result<Vec<Host>, String> {
+    let query = query::parse(query, &custom_field_names()?)?;
+    with_index(|index| {
+        index
+            .search(query.as_ref(), &HashMap::new())
+            .into_iter()
+            .map(|(host, _)| host.clone())
+            .collect()
+    })
+}
+
+// Lowercase names of every custom field some host has
+pub fn custom_field_names() -> Result<HashSet<String>, String> {
+    with_index(|index| {
+        index
+            .docs
+            .iter()
+            .flatten()
+            .flat_map(|doc| doc.host.fields.keys().map(|key| key.to_lowercase()))
+            .collect()
+    })
+}
+
+// Run `f` against the index, building it first if this is the first search
+fn with_index<R>(f: impl FnOnce(&SearchIndex) -> R) -> Result<R, String> {
+    let mut guard = INDEX.lock().map_err(|_| "Failed to acquire search index lock".to_string())?;
+    let index = match guard.take() {
+        Some(index) => index,
+        None => SearchIndex::build(&get_hosts()?),
+    };
+    Ok(f(guard.insert(index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{host, strings};
+    use std::collections::BTreeMap;
+
+    fn inventory() -> Vec<Host> {
+        (0..200)
+            .map(|i| Host {
+                description: format!("{} server {}", ["File", "SQL", "Web", "Print"][i % 4], i),
+                group: ["London", "Amsterdam"][i % 2].to_string(),
+                port: (i % 7 == 0).then_some(3390),
+                aliases: if i % 10 == 0 { strings(&[&format!("alias{}", i)]) } else { Vec::new() },
+                tags: strings(&[["prod", "test", "dev"][i % 3]]),
+                fields: BTreeMap::from([("site".to_string(), ["LON", "AMS", "NYC"][i % 3].to_string())]),
+                ..host(&format!("{}-srv{:03}", ["lon", "ams"][i % 2], i))
+            })
+            .collect()
+    }
+
+    // What searching without an index finds: every host the query matches,
+    // best score first, then in inventory order
+    fn scan<'a>(hosts: &'a [Host], query: &Query) -> Vec<&'a str> {
+        let mut scored: Vec<(i64, usize, &str)> = hosts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, h)| query.score(h).map(|score| (score, i, h.hostname.as_str())))
+            .collect();
+        scored.sort_by_key(|(score, i, _)| (std::cmp::Reverse(*score), *i));
+        scored.into_iter().map(|(_, _, name)| name).collect()
+    }
+
+    fn assert_same_as_scan(index: &SearchIndex, hosts: &[Host]) {
+        let custom_fields = HashSet::from(["site".to_string()]);
+        let no_usage = HashMap::new();
+        let queries = [
+            "srv01",
+            "lonsrv",
+            "sqll server",
+            "\"print server\"",
+            "tag:prod",
+            "site:lon -tag:dev",
+            "port:3390",
+            "desc:file OR alias:alias1",
+            "group:amsterdam (sql OR web)",
+            "-tag:test",
+            "zz",
+        ];
+        for text in queries {
+            let query = query::parse(text, &custom_fields).unwrap().unwrap();
+            let found: Vec<&str> = index
+                .search(Some(&query), &no_usage)
+                .into_iter()
+                .map(|(host, _)| host.hostname.as_str())
+                .collect();
+            assert_eq!(found, scan(hosts, &query), "{}", text);
+        }
+    }
+
+    #[test]
+    fn finds_what_a_full_scan_finds() {
+        let hosts = inventory();
+        assert_same_as_scan(&SearchIndex::build(&hosts), &hosts);
+    }
+
+    #[test]
+    fn stays_correct_as_hosts_change() {
+        let mut hosts = inventory();
+        let mut index = SearchIndex::build(&hosts);
+
+        hosts.retain(|h| !h.hostname.ends_with('5'));
+        hosts[3].description = "Print server moved".to_string();
+        hosts[4].tags = strings(&["prod"]);
+        hosts.insert(0, Host { description: "SQL server new".to_string(), ..host("nyc-srv999") });
+        index.update(&hosts);
+
+        assert_same_as_scan(&index, &hosts);
+        assert_eq!(index.docs.iter().flatten().count(), hosts.len());
+    }
+
+    #[test]
+    fn frequently_used_hosts_rank_higher() {
+        let hosts = vec![host("lon-sql01"), host("lon-sql02")];
+        let index = SearchIndex::build(&hosts);
+        let used: HostUsage = serde_json::from_value(serde_json::json!({
+            "last_connected": usage::now(),
+            "connections": 5,
+            "frecency": 5.0,
+        }))
+        .unwrap();
+        let usage = HashMap::from([("lon-sql02".to_string(), used)]);
+
+        let query = query::parse("sql", &HashSet::new()).unwrap().unwrap();
+        let found: Vec<&str> = index
+            .search(Some(&query), &usage)
+            .into_iter()
+            .map(|(host, _)| host.hostname.as_str())
+            .collect();
+        assert_eq!(found, vec!["lon-sql02", "lon-sql01"]);
+    }
+
+    #[test]
+    fn empty_search_keeps_inventory_order() {
+        let hosts = inventory();
+        let index = SearchIndex::build(&hosts);
+        let no_usage = HashMap::new();
+        let found: Vec<&Host> = index.search(None, &no_usage).into_iter().map(|(host, _)| host).collect();
+        assert_eq!(found, hosts.iter().collect::<Vec<_>>());
+    }
+}
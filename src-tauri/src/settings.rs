@@ -0,0 +1,48 @@
+use crate::csv_dialect::CsvDialect;
+use crate::inventory::InventoryFormat;
+use crate::{log_to_file, search_index, storage};
+use std::path::Path;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Where the hosts are kept, the extension picks CSV, JSON or YAML
+    pub inventory_file: String,
+    // Delimiter and encoding used whenever a CSV inventory is written
+    pub csv_output: CsvDialect,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            inventory_file: "hosts.csv".to_string(),
+            csv_output: CsvDialect::default(),
+        }
+    }
+}
+
+// A broken settings file shouldn't stop hosts from being saved, so fall back
+// to the defaults and leave a note in the log
+pub fn load() -> Settings {
+    storage::load_json(SETTINGS_FILE).unwrap_or_else(|e| {
+        log_to_file(&format!("Using default settings: {}", e));
+        Settings::default()
+    })
+}
+
+#[tauri::command]
+pub fn get_settings() -> Result<Settings, String> {
+    storage::load_json(SETTINGS_FILE)
+}
+
+#[tauri::command]
+pub fn save_settings(settings: Settings) -> Result<(), String> {
+    InventoryFormat::from_path(Path::new(&settings.inventory_file))?;
+    settings.csv_output.delimiter_byte()?;
+    storage::save_json(SETTINGS_FILE, &settings)?;
+    // The inventory file may have changed, searches have to start over
+    search_index::reset();
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use crate::{diff, get_hosts, inventory, log_to_file, read_hosts_file, write_inventory};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SNAPSHOT_DIR: &str = "snapshots";
+
+// Retention policy: never keep more than MAX_SNAPSHOTS, drop anything older
+// than MAX_AGE_DAYS, but always keep the MIN_SNAPSHOTS most recent ones
+const MAX_SNAPSHOTS: usize = 50;
+const MIN_SNAPSHOTS: usize = 5;
+const MAX_AGE_DAYS: u64 = 30;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SnapshotInfo {
+    id: String,
+    created_at: u64,
+    reason: String,
+    host_count: usize,
+    size_bytes: u64,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+// Copy the current inventory aside before it gets overwritten, keeping its
+// format. Returns None when there is no inventory yet, so nothing needed saving.
+pub fn create_snapshot(reason: &str) -> Result<Option<String>, String> {
+    let source = inventory::path();
+    if !source.exists() {
+        return Ok(None);
+    }
+
+    std::fs::create_dir_all(SNAPSHOT_DIR)
+        .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let reason: String = reason
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let id = format!("{}-{}", millis, reason);
+
+    let extension = source
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "csv".to_string());
+    let target = Path::new(SNAPSHOT_DIR).join(format!("hosts-{}.{}", id, extension));
+
+    std::fs::copy(&source, target)
+        .map_err(|e| format!("Failed to snapshot {}: {}", source.display(), e))?;
+    log_to_file(&format!("Created hosts snapshot {}", id));
+
+    if let Err(e) = apply_retention() {
+        log_to_file(&format!("Failed to prune old snapshots: {}", e));
+    }
+
+    Ok(Some(id))
+}
+
+// Snapshot ids come back from the frontend, so make sure they can't escape the directory
+fn existing_snapshot_path(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid snapshot id: {}", id));
+    }
+    read_snapshots()?
+        .into_iter()
+        .find(|snapshot| snapshot.id == id)
+        .map(|snapshot| snapshot.path)
+        .ok_or(format!("Snapshot not found: {}", id))
+}
+
+// Snapshot file names look like hosts-<millis>-<reason>.<csv|json|yaml>
+fn parse_snapshot_name(file_name: &str) -> Option<(String, u64, String)> {
+    let (id, extension) = file_name.strip_prefix("hosts-")?.rsplit_once('.')?;
+    if !matches!(extension, "csv" | "json" | "yaml" | "yml") {
+        return None;
+    }
+    let (millis, reason) = id.split_once('-')?;
+    let millis: u64 = millis.parse().ok()?;
+    Some((id.to_string(), millis / 1000, reason.to_string()))
+}
+
+fn read_snapshots() -> Result<Vec<SnapshotInfo>, String> {
+    let dir = Path::new(SNAPSHOT_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read snapshot directory: {}", e))?;
+
+    let mut snapshots = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if let Some((id, created_at, reason)) = parse_snapshot_name(&file_name) {
+            let size_bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            let host_count = read_hosts_file(&entry.path()).map(|h| h.len()).unwrap_or(0);
+            snapshots.push(SnapshotInfo { id, created_at, reason, host_count, size_bytes, path: entry.path() });
+        }
+    }
+
+    // Newest first
+    snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(snapshots)
+}
+
+// Whether the snapshot at `index`, counting from the newest, falls outside the
+// retention policy
+fn is_expired(index: usize, created_at: u64, now: u64) -> bool {
+    let max_age = MAX_AGE_DAYS * 24 * 60 * 60;
+    let too_many = index >= MAX_SNAPSHOTS;
+    let too_old = index >= MIN_SNAPSHOTS && now.saturating_sub(created_at) > max_age;
+    too_many || too_old
+}
+
+fn apply_retention() -> Result<(), String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    for (index, snapshot) in read_snapshots()?.iter().enumerate() {
+        if is_expired(index, snapshot.created_at, now) {
+            std::fs::remove_file(&snapshot.path)
+                .map_err(|e| format!("Failed to remove snapshot {}: {}", snapshot.id, e))?;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_snapshots() -> Result<Vec<SnapshotInfo>, String> {
+    read_snapshots()
+}
+
+// Changes made to the inventory since the snapshot was taken
+#[tauri::command]
+pub fn diff_snapshot(id: String) -> Result<diff::HostsDiff, String> {
+    let snapshot_hosts = read_hosts_file(&existing_snapshot_path(&id)?)?;
+    let current_hosts = get_hosts()?;
+    Ok(diff::diff_hosts(&snapshot_hosts, &current_hosts))
+}
+
+#[tauri::command]
+pub fn restore_snapshot(id: String) -> Result<(), String> {
+    // Loaded and written back rather than copied, in case the inventory has
+    // moved to another format since the snapshot was taken
+    // The snapshot's invalid rows come along too
+    let snapshot = inventory::load(&existing_snapshot_path(&id)?)?;
+
+    // Make sure the restore itself can be undone
+    create_snapshot("before-restore")?;
+
+    write_inventory(&snapshot.hosts, &snapshot.unparsed).map_err(|e| format!("Failed to restore snapshot: {}", e))?;
+    log_to_file(&format!("Restored hosts snapshot {}", id));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_snapshot_file_names() {
+        assert_eq!(
+            parse_snapshot_name("hosts-1700000000123-merge.csv"),
+            Some(("1700000000123-merge".to_string(), 1700000000, "merge".to_string()))
+        );
+        assert!(parse_snapshot_name("hosts-1700000000123-import.yaml").is_some());
+        assert_eq!(parse_snapshot_name("hosts-1700000000123-import.txt"), None);
+        assert_eq!(parse_snapshot_name("hosts-latest-import.csv"), None);
+        assert_eq!(parse_snapshot_name("hosts-1700000000123.csv"), None);
+        assert_eq!(parse_snapshot_name("recent.json"), None);
+    }
+
+    #[test]
+    fn keeps_the_newest_snapshots_however_old() {
+        let day = 24 * 60 * 60;
+        let now = 1000 * day;
+        let old = now - (MAX_AGE_DAYS + 1) * day;
+
+        assert!(!is_expired(MIN_SNAPSHOTS - 1, old, now));
+        assert!(is_expired(MIN_SNAPSHOTS, old, now));
+        assert!(!is_expired(MIN_SNAPSHOTS, now - day, now));
+        assert!(is_expired(MAX_SNAPSHOTS, now, now));
+    }
+}
@@ -0,0 +1,442 @@
+use crate::{get_hosts, history, inventory, log_to_file, rules, snapshots, trash, validation, write_hosts, Host};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Only one bulk change can be waiting for review at a time, staging a new one
+// replaces it
+static STAGED: Mutex<Option<StagedImport>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StageMode {
+    // The incoming hosts are the whole inventory (domain scan, replacing the
+    // file), so local hosts missing from them are removed
+    Replace,
+    // The incoming hosts are added to the inventory, nothing is removed
+    Merge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    KeepLocal,
+    TakeIncoming,
+    Merge,
+}
+
+struct StagedImport {
+    id: String,
+    source: String,
+    mode: StageMode,
+    // The inventory the changes were computed against
+    base: Vec<Host>,
+    incoming: Vec<Host>,
+    skipped: Vec<String>,
+    // Lowercased hostnames of incoming hosts skipped for a name clash. Their
+    // local copies are kept as they are, rather than counted as removed.
+    held: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HostConflict {
+    hostname: String,
+    local: Host,
+    incoming: Host,
+    // What a merge would produce, so it can be previewed
+    merged: Host,
+    fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StagedChanges {
+    id: String,
+    source: String,
+    added: Vec<Host>,
+    removed: Vec<Host>,
+    conflicts: Vec<HostConflict>,
+    unchanged: usize,
+    skipped: Vec<String>,
+}
+
+impl StagedChanges {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} changed, {} unchanged",
+            self.added.len(),
+            self.removed.len(),
+            self.conflicts.len(),
+            self.unchanged
+        )
+    }
+}
+
+fn changed_fields(local: &Host, incoming: &Host) -> Vec<String> {
+    let mut fields = Vec::new();
+    if local.hostname != incoming.hostname {
+        fields.push("hostname");
+    }
+    if local.description != incoming.description {
+        fields.push("description");
+    }
+    if local.group != incoming.group {
+        fields.push("group");
+    }
+    if local.port != incoming.port {
+        fields.push("port");
+    }
+    if local.gateway != incoming.gateway {
+        fields.push("gateway");
+    }
+    if local.aliases != incoming.aliases {
+        fields.push("aliases");
+    }
+    if local.os != incoming.os {
+        fields.push("os");
+    }
+    if local.tags != incoming.tags {
+        fields.push("tags");
+    }
+    if local.fields != incoming.fields {
+        fields.push("fields");
+    }
+    if local.favorite != incoming.favorite {
+        fields.push("favorite");
+    }
+    if local.rdp != incoming.rdp {
+        fields.push("rdp");
+    }
+    fields.into_iter().map(str::to_string).collect()
+}
+
+// Incoming values win where they are set, local ones fill the gaps. Aliases,
+// tags and custom fields from both sides are kept, as is a favorite from either.
+fn merge_host(local: &Host, incoming: &Host) -> Host {
+    let pick = |incoming: &str, local: &str| {
+        if incoming.trim().is_empty() { local.to_string() } else { incoming.to_string() }
+    };
+
+    let mut tags = local.tags.clone();
+    for tag in &incoming.tags {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.clone());
+        }
+    }
+
+    let mut aliases = local.aliases.clone();
+    for alias in &incoming.aliases {
+        if !aliases.iter().any(|a| a.eq_ignore_ascii_case(alias)) {
+            aliases.push(alias.clone());
+        }
+    }
+
+    Host {
+        hostname: incoming.hostname.clone(),
+        description: pick(&incoming.description, &local.description),
+        group: pick(&incoming.group, &local.group),
+        port: incoming.port.or(local.port),
+        gateway: pick(&incoming.gateway, &local.gateway),
+        aliases,
+        os: pick(&incoming.os, &local.os),
+        tags,
+        fields: local.fields.clone().into_iter().chain(incoming.fields.clone()).collect(),
+        favorite: local.favorite || incoming.favorite,
+        rdp: local.rdp.overlay(&incoming.rdp),
+    }
+}
+
+fn compute_changes(staged: &StagedImport) -> StagedChanges {
+    let local_by_name: HashMap<String, &Host> = staged
+        .base
+        .iter()
+        .map(|h| (h.hostname.to_lowercase(), h))
+        .collect();
+    let incoming_names: Vec<String> = staged.incoming.iter().map(|h| h.hostname.to_lowercase()).collect();
+
+    let mut added = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut unchanged = 0;
+    for incoming in &staged.incoming {
+        match local_by_name.get(&incoming.hostname.to_lowercase()) {
+            None => added.push(incoming.clone()),
+            Some(local) if *local == incoming => unchanged += 1,
+            Some(local) => conflicts.push(HostConflict {
+                hostname: local.hostname.clone(),
+                fields: changed_fields(local, incoming),
+                merged: merge_host(local, incoming),
+                local: (*local).clone(),
+                incoming: incoming.clone(),
+            }),
+        }
+    }
+
+    let removed = match staged.mode {
+        StageMode::Replace => staged
+            .base
+            .iter()
+            .filter(|h| {
+                let name = h.hostname.to_lowercase();
+                !incoming_names.contains(&name) && !staged.held.contains(&name)
+            })
+            .cloned()
+            .collect(),
+        StageMode::Merge => Vec::new(),
+    };
+
+    StagedChanges {
+        id: staged.id.clone(),
+        source: staged.source.clone(),
+        added,
+        removed,
+        conflicts,
+        unchanged,
+        skipped: staged.skipped.clone(),
+    }
+}
+
+// Hold a bulk change for review instead of writing it. Incoming hosts are
+// expected to be validated already; `skipped` lists what the source dropped,
+// and gets the hosts left out for name clashes added to it.
+pub fn stage(source: &str, mode: StageMode, incoming: Vec<Host>, mut skipped: Vec<String>) -> Result<StagedChanges, String> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let base = get_hosts()?;
+
+    // Derive tags and fields from the hostnames, like a host saved by hand
+    let rules = rules::load_rules();
+
+    let incoming: Vec<Host> = incoming.iter().map(|host| rules::apply_rules(host, &rules)).collect();
+    let (accepted, held) = accept_incoming(mode, &base, incoming, &mut skipped);
+
+    let staged = StagedImport {
+        id: millis.to_string(),
+        source: source.to_string(),
+        mode,
+        base,
+        incoming: accepted,
+        skipped,
+        held,
+    };
+    let changes = compute_changes(&staged);
+
+    let mut current = STAGED.lock().map_err(|_| "Failed to acquire staging lock".to_string())?;
+    *current = Some(staged);
+
+    log_to_file(&format!("Staged changes from {}: {}", source, changes.summary()));
+    Ok(changes)
+}
+
+// Hosts whose hostname or aliases clash with another host's are left out and
+// reported in `skipped`, along with the lowercased names of the ones left out.
+// When merging, the local hosts stay, so they count too.
+fn accept_incoming(mode: StageMode, base: &[Host], incoming: Vec<Host>, skipped: &mut Vec<String>) -> (Vec<Host>, Vec<String>) {
+    let mut accepted: Vec<Host> = Vec::new();
+    let mut held = Vec::new();
+    for host in incoming {
+        let local = base
+            .iter()
+            .filter(|h| mode == StageMode::Merge && !h.hostname.eq_ignore_ascii_case(&host.hostname));
+        match validation::name_conflict(&host, accepted.iter().chain(local)) {
+            Some(conflict) => {
+                skipped.push(conflict);
+                held.push(host.hostname.to_lowercase());
+            }
+            None => accepted.push(host),
+        }
+    }
+    (accepted, held)
+}
+
+#[tauri::command]
+pub fn get_staged_changes() -> Result<Option<StagedChanges>, String> {
+    let current = STAGED.lock().map_err(|_| "Failed to acquire staging lock".to_string())?;
+    Ok(current.as_ref().map(compute_changes))
+}
+
+#[tauri::command]
+pub fn discard_staged_changes() -> Result<(), String> {
+    let mut current = STAGED.lock().map_err(|_| "Failed to acquire staging lock".to_string())?;
+    if let Some(staged) = current.take() {
+        log_to_file(&format!("Discarded staged changes from {}", staged.source));
+    }
+    Ok(())
+}
+
+// Apply the staged changes in one write. Every conflict (and, when replacing,
+// every removal) needs a resolution, either its own or the default one.
+// Removals only know keep_local and take_incoming; merge keeps the host.
+#[tauri::command]
+pub fn apply_staged_changes(
+    id: String,
+    resolutions: HashMap<String, Resolution>,
+    default_resolution: Option<Resolution>,
+) -> Result<String, String> {
+    let mut current = STAGED.lock().map_err(|_| "Failed to acquire staging lock".to_string())?;
+    let staged = match current.as_ref() {
+        Some(staged) if staged.id == id => staged,
+        _ => return Err("These changes are no longer staged, run the import again".to_string()),
+    };
+
+    // Someone may have edited the inventory while the preview was open
+    if get_hosts()? != staged.base {
+        return Err("The inventory changed since these changes were staged, run the import again".to_string());
+    }
+
+    let changes = compute_changes(staged);
+    let resolution_for = |hostname: &str| {
+        resolutions
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(hostname))
+            .map(|(_, resolution)| *resolution)
+            .or(default_resolution)
+    };
+
+    let unresolved: Vec<&str> = changes
+        .conflicts
+        .iter()
+        .map(|c| c.hostname.as_str())
+        .chain(changes.removed.iter().map(|h| h.hostname.as_str()))
+        .filter(|hostname| resolution_for(hostname).is_none())
+        .collect();
+    if !unresolved.is_empty() {
+        return Err(format!("Choose what to do with: {}", unresolved.join(", ")));
+    }
+
+    let conflicts: HashMap<String, &HostConflict> = changes
+        .conflicts
+        .iter()
+        .map(|c| (c.hostname.to_lowercase(), c))
+        .collect();
+
+    let mut hosts = Vec::new();
+    let mut deleted = Vec::new();
+    for local in &staged.base {
+        let key = local.hostname.to_lowercase();
+        if let Some(conflict) = conflicts.get(&key) {
+            hosts.push(match resolution_for(&local.hostname) {
+                Some(Resolution::TakeIncoming) => conflict.incoming.clone(),
+                Some(Resolution::Merge) => conflict.merged.clone(),
+                _ => local.clone(),
+            });
+        } else if changes.removed.iter().any(|h| h.hostname == local.hostname) {
+            if resolution_for(&local.hostname) == Some(Resolution::TakeIncoming) {
+                deleted.push(local.clone());
+            } else {
+                hosts.push(local.clone());
+            }
+        } else {
+            hosts.push(local.clone());
+        }
+    }
+    hosts.extend(changes.added.iter().cloned());
+
+    // Merging aliases or keeping local hosts can still make names clash
+    let name_conflicts = validation::name_conflicts(&hosts);
+    if !name_conflicts.is_empty() {
+        return Err(format!("Resolve these name conflicts first:\n{}", name_conflicts.join("\n")));
+    }
+
+    snapshots::create_snapshot("import")?;
+    trash::move_to_trash(&deleted)?;
+    write_hosts(&hosts)?;
+    history::record_all("imported", &staged.base, &hosts);
+
+    let summary = changes.summary();
+    log_to_file(&format!("Applied staged changes from {}: {}", staged.source, summary));
+    *current = None;
+
+    Ok(format!("Applied changes from {}: {}", changes.source, summary))
+}
+
+// Stage a whole hosts file (CSV, JSON or YAML) as the new inventory, for
+// replacing the inventory with an edited or exported copy
+#[tauri::command]
+pub fn stage_hosts_file(path: String) -> Result<StagedChanges, String> {
+    let report = inventory::load(Path::new(&path))?;
+    let skipped = report
+        .problems
+        .iter()
+        .map(|problem| format!("Line {}: {}", problem.line, problem.reason))
+        .collect();
+
+    stage(&path, StageMode::Replace, report.hosts, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{host, strings};
+
+    fn staged(mode: StageMode, base: Vec<Host>, incoming: Vec<Host>) -> StagedChanges {
+        let mut skipped = Vec::new();
+        let (incoming, held) = accept_incoming(mode, &base, incoming, &mut skipped);
+        compute_changes(&StagedImport {
+            id: "1".to_string(),
+            source: "test".to_string(),
+            mode,
+            base,
+            incoming,
+            skipped,
+            held,
+        })
+    }
+
+    fn names(hosts: &[Host]) -> Vec<&str> {
+        hosts.iter().map(|h| h.hostname.as_str()).collect()
+    }
+
+    #[test]
+    fn replacing_removes_hosts_missing_from_the_import() {
+        let changes = staged(
+            StageMode::Replace,
+            vec![host("srv01"), host("srv02")],
+            vec![host("SRV01"), Host { port: Some(3390), ..host("srv03") }],
+        );
+        assert_eq!(names(&changes.added), vec!["srv03"]);
+        assert_eq!(names(&changes.removed), vec!["srv02"]);
+        assert_eq!(changes.conflicts.len(), 1);
+        assert_eq!(changes.conflicts[0].fields, vec!["hostname"]);
+    }
+
+    #[test]
+    fn hosts_skipped_for_a_name_clash_are_not_removed() {
+        let base = vec![host("srv01"), host("srv02")];
+        let incoming = vec![
+            Host { aliases: strings(&["files"]), ..host("srv01") },
+            Host { aliases: strings(&["FILES"]), ..host("srv02") },
+        ];
+
+        let changes = staged(StageMode::Replace, base, incoming);
+        assert_eq!(changes.skipped.len(), 1);
+        assert!(changes.removed.is_empty());
+        assert_eq!(changes.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn merging_checks_names_against_local_hosts() {
+        let base = vec![Host { aliases: strings(&["files"]), ..host("srv01") }];
+        let changes = staged(StageMode::Merge, base, vec![Host { aliases: strings(&["files"]), ..host("srv02") }]);
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.is_empty());
+        assert_eq!(changes.skipped.len(), 1);
+    }
+
+    #[test]
+    fn merge_keeps_values_from_both_sides() {
+        let local = Host {
+            description: "File server".to_string(),
+            tags: strings(&["prod"]),
+            favorite: true,
+            ..host("srv01")
+        };
+        let incoming = Host { port: Some(3390), tags: strings(&["PROD", "files"]), ..host("srv01") };
+
+        let merged = merge_host(&local, &incoming);
+        assert_eq!(merged.description, "File server");
+        assert_eq!(merged.port, Some(3390));
+        assert_eq!(merged.tags, strings(&["prod", "files"]));
+        assert!(merged.favorite);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::write_file_atomic;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+// Small JSON files next to hosts.csv hold everything that isn't the inventory
+// itself. A missing file just means nothing has been saved yet.
+pub fn load_json<T: DeserializeOwned + Default>(path: &str) -> Result<T, String> {
+    let path = Path::new(path);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+pub fn save_json<T: Serialize>(path: &str, value: &T) -> Result<(), String> {
+    let contents = serde_json::to_vec_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path, e))?;
+    write_file_atomic(Path::new(path), &contents)
+        .map_err(|e| format!("Failed to write {}: {}", path, e))
+}
@@ -0,0 +1,59 @@
+// Fixtures shared by the test modules
+use crate::rdp::RdpSettings;
+use crate::Host;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// A host with only a hostname, set the rest with struct update syntax:
+// Host { port: Some(3390), ..host("srv01") }
+pub fn host(hostname: &str) -> Host {
+    Host {
+        hostname: hostname.to_string(),
+        description: String::new(),
+        group: String::new(),
+        port: None,
+        gateway: String::new(),
+        aliases: Vec::new(),
+        os: String::new(),
+        tags: Vec::new(),
+        fields: BTreeMap::new(),
+        favorite: false,
+        rdp: RdpSettings::default(),
+    }
+}
+
+// Every Host field set, with text CSV has to quote
+pub fn full_host() -> Host {
+    Host {
+        hostname: "lon-sql01.corp.example.com".to_string(),
+        description: "Primary \"SQL\" node, London\nsecond line".to_string(),
+        group: "London/SQL".to_string(),
+        port: Some(3390),
+        gateway: "rdgw.corp.example.com:4443".to_string(),
+        aliases: strings(&["sqlcluster", "oldsql01"]),
+        os: "Windows Server 2019 Standard".to_string(),
+        tags: strings(&["prod", "Café"]),
+        fields: BTreeMap::from([
+            ("role".to_string(), "SQL".to_string()),
+            ("site".to_string(), "LON".to_string()),
+        ]),
+        favorite: true,
+        rdp: RdpSettings {
+            full_screen: Some(false),
+            desktop_width: Some(1920),
+            desktop_height: Some(1080),
+            redirect_clipboard: Some(true),
+            redirect_printers: Some(false),
+            redirect_drives: None,
+        },
+    }
+}
+
+pub fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+// A file in the temp directory no other test or test run uses
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("connectx-test-{}-{}", std::process::id(), name))
+}
@@ -0,0 +1,149 @@
+use crate::{get_hosts, history, log_to_file, storage, validation, write_hosts, Host};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TRASH_FILE: &str = "trash.json";
+
+// Deleted hosts are purged for good after this long
+const RETENTION_DAYS: u64 = 30;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeletedHost {
+    host: Host,
+    // Seconds since the epoch
+    deleted_at: u64,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct TrashStore {
+    hosts: Vec<DeletedHost>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// Load the trash with anything past the retention period already dropped
+fn load_store() -> Result<TrashStore, String> {
+    let mut store: TrashStore = storage::load_json(TRASH_FILE)?;
+    let cutoff = now().saturating_sub(RETENTION_DAYS * 24 * 60 * 60);
+
+    let before = store.hosts.len();
+    store.hosts.retain(|deleted| deleted.deleted_at >= cutoff);
+    let expired = before - store.hosts.len();
+    if expired > 0 {
+        storage::save_json(TRASH_FILE, &store)?;
+        log_to_file(&format!("Purged {} deleted host(s) older than {} days", expired, RETENTION_DAYS));
+    }
+
+    Ok(store)
+}
+
+// Keep hosts that are about to be removed from the inventory, so the removal
+// can be undone. Call this before writing the inventory without them.
+pub fn move_to_trash(hosts: &[Host]) -> Result<(), String> {
+    if hosts.is_empty() {
+        return Ok(());
+    }
+
+    let mut store = load_store()?;
+    let deleted_at = now();
+    store.hosts.extend(hosts.iter().map(|host| DeletedHost { host: host.clone(), deleted_at }));
+    storage::save_json(TRASH_FILE, &store)
+}
+
+// Drop the newest trash entry for a host that was brought back another way
+pub fn take_back(hostname: &str) -> Result<(), String> {
+    let mut store = load_store()?;
+    if let Some(index) = find_entry(&store.hosts, hostname, None) {
+        store.hosts.remove(index);
+        storage::save_json(TRASH_FILE, &store)?;
+    }
+    Ok(())
+}
+
+// Most recently deleted first
+#[tauri::command]
+pub fn list_deleted_hosts() -> Result<Vec<DeletedHost>, String> {
+    let mut hosts = load_store()?.hosts;
+    hosts.sort_by_key(|d| std::cmp::Reverse(d.deleted_at));
+    Ok(hosts)
+}
+
+// Index of the newest entry for the hostname, or the one deleted at `deleted_at`
+fn find_entry(hosts: &[DeletedHost], hostname: &str, deleted_at: Option<u64>) -> Option<usize> {
+    hosts
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| d.host.hostname.eq_ignore_ascii_case(hostname) && deleted_at.is_none_or(|at| d.deleted_at == at))
+        .max_by_key(|(_, d)| d.deleted_at)
+        .map(|(i, _)| i)
+}
+
+// Put a deleted host back into the inventory. The same hostname can be in the
+// trash more than once; deleted_at picks the entry, otherwise the newest wins.
+#[tauri::command]
+pub fn restore_host(hostname: String, deleted_at: Option<u64>) -> Result<Host, String> {
+    let mut store = load_store()?;
+
+    let index = find_entry(&store.hosts, &hostname, deleted_at)
+        .ok_or(format!("{} is not in the deleted hosts", hostname))?;
+
+    let mut hosts = get_hosts()?;
+    if hosts.iter().any(|h| h.hostname.eq_ignore_ascii_case(&hostname)) {
+        return Err(format!("{} already exists in the inventory", hostname));
+    }
+    // An alias may have been given to another host since
+    if let Some(conflict) = validation::name_conflict(&store.hosts[index].host, &hosts) {
+        return Err(conflict);
+    }
+
+    let restored = store.hosts.remove(index).host;
+    hosts.push(restored.clone());
+    write_hosts(&hosts)?;
+    storage::save_json(TRASH_FILE, &store)?;
+    history::record("restored", None, Some(&restored));
+
+    log_to_file(&format!("Restored deleted host {}", hostname));
+    Ok(restored)
+}
+
+// Permanently remove the given hosts from the trash, or everything when no
+// hostnames are given. Returns how many entries were purged.
+#[tauri::command]
+pub fn purge_deleted(hostnames: Option<Vec<String>>) -> Result<usize, String> {
+    let mut store = load_store()?;
+
+    let before = store.hosts.len();
+    match hostnames {
+        Some(names) => store.hosts.retain(|d| !names.iter().any(|n| n.eq_ignore_ascii_case(&d.host.hostname))),
+        None => store.hosts.clear(),
+    }
+    let purged = before - store.hosts.len();
+
+    storage::save_json(TRASH_FILE, &store)?;
+    log_to_file(&format!("Purged {} deleted host(s)", purged));
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host;
+
+    #[test]
+    fn finds_entries_ignoring_case() {
+        let trash = vec![
+            DeletedHost { host: host("srv01"), deleted_at: 100 },
+            DeletedHost { host: host("SRV01"), deleted_at: 200 },
+            DeletedHost { host: host("srv02"), deleted_at: 300 },
+        ];
+        assert_eq!(find_entry(&trash, "Srv01", None), Some(1));
+        assert_eq!(find_entry(&trash, "srv01", Some(100)), Some(0));
+        assert_eq!(find_entry(&trash, "srv01", Some(300)), None);
+        assert_eq!(find_entry(&trash, "srv03", None), None);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{log_to_file, storage};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE_FILE: &str = "usage.json";
+
+// A connection counts half as much after a week, a quarter after two
+const HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
+// How much connecting often and recently adds to a search score. The bonus
+// grows with the log of the frecency, so a host used every day doesn't bury
+// better matches: one recent connection adds about 14, a hundred about 92.
+const FRECENCY_WEIGHT: f64 = 20.0;
+
+// Hosts whose frecency decayed below this are forgotten
+const MIN_FRECENCY: f64 = 0.01;
+
+// Loaded on first use, searches read it on every keystroke
+static USAGE: Mutex<Option<UsageStore>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct HostUsage {
+    // Seconds since the epoch
+    pub last_connected: u64,
+    connections: u64,
+    // Decayed connection count as of last_connected
+    frecency: f64,
+}
+
+impl HostUsage {
+    fn frecency_at(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_connected) as f64;
+        self.frecency * 0.5f64.powf(age / HALF_LIFE_SECS)
+    }
+
+    // Added to the match score of the host in search results
+    pub fn search_bonus(&self, now: u64) -> i64 {
+        (FRECENCY_WEIGHT * self.frecency_at(now).ln_1p()).round() as i64
+    }
+}
+
+// Keyed by lowercase hostname
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct UsageStore {
+    hosts: HashMap<String, HostUsage>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn load_store() -> UsageStore {
+    storage::load_json(USAGE_FILE).unwrap_or_else(|e| {
+        log_to_file(&format!("Failed to load usage, starting over: {}", e));
+        UsageStore::default()
+    })
+}
+
+// Run `f` with the usage of every host, keyed by lowercase hostname
+pub fn with_usage<R>(f: impl FnOnce(&HashMap<String, HostUsage>) -> R) -> R {
+    match USAGE.lock() {
+        Ok(mut usage) => f(&usage.get_or_insert_with(load_store).hosts),
+        Err(_) => {
+            log_to_file("Failed to acquire usage lock");
+            f(&HashMap::new())
+        }
+    }
+}
+
+// Count a connection to the host. Usage only affects the order of search
+// results, so failing to save it is logged rather than failing the launch.
+pub fn record_connection(hostname: &str) {
+    let Ok(mut usage) = USAGE.lock() else {
+        log_to_file("Failed to acquire usage lock");
+        return;
+    };
+    let store = usage.get_or_insert_with(load_store);
+    count_connection(&mut store.hosts, hostname, now());
+
+    if let Err(e) = storage::save_json(USAGE_FILE, store) {
+        log_to_file(&format!("Failed to save usage: {}", e));
+    }
+}
+
+// Add a connection made at `now` and forget hosts not used in a long time
+fn count_connection(hosts: &mut HashMap<String, HostUsage>, hostname: &str, now: u64) {
+    let entry = hosts.entry(hostname.to_lowercase()).or_insert(HostUsage {
+        last_connected: now,
+        connections: 0,
+        frecency: 0.0,
+    });
+    entry.frecency = entry.frecency_at(now) + 1.0;
+    entry.connections += 1;
+    entry.last_connected = now;
+
+    hosts.retain(|_, usage| usage.frecency_at(now) >= MIN_FRECENCY);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEEK: u64 = 7 * 24 * 60 * 60;
+
+    #[test]
+    fn connections_decay_by_half_every_week() {
+        let mut hosts = HashMap::new();
+        count_connection(&mut hosts, "SRV01", 1000);
+        count_connection(&mut hosts, "srv01", 1000 + WEEK);
+
+        let usage = hosts["srv01"];
+        assert_eq!(usage.connections, 2);
+        assert_eq!(usage.last_connected, 1000 + WEEK);
+        assert!((usage.frecency - 1.5).abs() < 1e-9);
+        assert!((usage.frecency_at(1000 + 2 * WEEK) - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bonus_grows_slowly_with_use() {
+        let usage = |frecency| HostUsage { last_connected: 0, connections: 1, frecency };
+        assert_eq!(usage(1.0).search_bonus(0), 14);
+        assert_eq!(usage(100.0).search_bonus(0), 92);
+        assert_eq!(usage(1.0).search_bonus(52 * WEEK), 0);
+    }
+
+    #[test]
+    fn forgets_hosts_not_used_in_a_long_time() {
+        let mut hosts = HashMap::new();
+        count_connection(&mut hosts, "srv01", 0);
+        count_connection(&mut hosts, "srv02", 10 * WEEK);
+        assert!(!hosts.contains_key("srv01"));
+        assert!(hosts.contains_key("srv02"));
+    }
+}
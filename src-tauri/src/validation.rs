@@ -0,0 +1,365 @@
+use crate::Host;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv6Addr};
+
+const MAX_HOSTNAME_LENGTH: usize = 253;
+const MAX_LABEL_LENGTH: usize = 63;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+// Collapse field errors into the single message commands return
+pub fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Split "host", "host:port", "[v6]" or "[v6]:port" into host and port.
+// A bare IPv6 literal is accepted without brackets as long as it has no port.
+pub fn parse_address(input: &str) -> Result<(String, Option<u16>), String> {
+    let input = input.trim();
+
+    if let Some(rest) = input.strip_prefix('[') {
+        let (host, after) = rest
+            .split_once(']')
+            .ok_or("Missing closing ']' after IPv6 address".to_string())?;
+        host.parse::<Ipv6Addr>()
+            .map_err(|_| format!("'{}' is not a valid IPv6 address", host))?;
+        let port = match after {
+            "" => None,
+            _ => Some(parse_port(
+                after.strip_prefix(':').ok_or(format!("Unexpected '{}' after IPv6 address", after))?,
+            )?),
+        };
+        return Ok((host.to_string(), port));
+    }
+
+    // More than one colon can only be an IPv6 address
+    if input.matches(':').count() > 1 {
+        input.parse::<Ipv6Addr>()
+            .map_err(|_| format!("'{}' is not a valid IPv6 address, use [address]:port to add a port", input))?;
+        return Ok((input.to_string(), None));
+    }
+
+    match input.split_once(':') {
+        Some((host, port)) => Ok((host.to_string(), Some(parse_port(port)?))),
+        None => Ok((input.to_string(), None)),
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, String> {
+    match port.trim().parse::<u16>() {
+        Ok(0) | Err(_) => Err(format!("'{}' is not a valid port (1-65535)", port)),
+        Ok(port) => Ok(port),
+    }
+}
+
+// Check an IP literal or RFC 1123 hostname and return the form to store:
+// IP addresses in canonical notation, internationalized names converted to
+// punycode, and plain ASCII names as typed
+pub fn validate_hostname(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Hostname cannot be empty".to_string());
+    }
+
+    if let Ok(ip) = name.parse::<IpAddr>() {
+        return Ok(ip.to_string());
+    }
+
+    let ascii = if name.is_ascii() {
+        name.to_string()
+    } else {
+        idna::domain_to_ascii(name)
+            .map_err(|_| format!("'{}' is not a valid internationalized hostname", name))?
+    };
+
+    // A single trailing dot marks a fully qualified name and is allowed
+    let trimmed = ascii.strip_suffix('.').unwrap_or(&ascii);
+    if trimmed.len() > MAX_HOSTNAME_LENGTH {
+        return Err(format!("Hostname is longer than {} characters", MAX_HOSTNAME_LENGTH));
+    }
+
+    let labels: Vec<&str> = trimmed.split('.').collect();
+    for label in &labels {
+        if label.is_empty() {
+            return Err("Hostname cannot contain empty labels ('..')".to_string());
+        }
+        if label.len() > MAX_LABEL_LENGTH {
+            return Err(format!("'{}' is longer than {} characters", label, MAX_LABEL_LENGTH));
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("'{}' may only contain letters, digits and hyphens", label));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("'{}' cannot start or end with a hyphen", label));
+        }
+    }
+
+    // Something like 10.0.0.300 is a mistyped address, not a name
+    if labels.len() > 1 && labels.last().is_some_and(|l| l.chars().all(|c| c.is_ascii_digit())) {
+        return Err(format!("'{}' is not a valid IP address or hostname", name));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+// Validate every field of a host and return it in normalized form. A port
+// typed as part of the hostname ("srv01:3390") is moved into the port field.
+pub fn normalize_host(host: &Host) -> Result<Host, Vec<FieldError>> {
+    let mut errors = Vec::new();
+    let mut normalized = host.clone();
+
+    match parse_address(&host.hostname) {
+        Ok((name, port)) => {
+            match validate_hostname(&name) {
+                Ok(name) => normalized.hostname = name,
+                Err(message) => errors.push(FieldError::new("hostname", message)),
+            }
+            if let Some(port) = port {
+                match host.port {
+                    Some(existing) if existing != port => errors.push(FieldError::new(
+                        "port",
+                        format!("Hostname specifies port {} but the port is set to {}", port, existing),
+                    )),
+                    _ => normalized.port = Some(port),
+                }
+            }
+        }
+        Err(message) => errors.push(FieldError::new("hostname", message)),
+    }
+
+    if host.port == Some(0) {
+        errors.push(FieldError::new("port", "Port must be between 1 and 65535"));
+    }
+
+    if !host.gateway.trim().is_empty() {
+        match parse_address(&host.gateway) {
+            Ok((name, port)) => match validate_hostname(&name) {
+                Ok(name) => normalized.gateway = format_address(&name, port),
+                Err(message) => errors.push(FieldError::new("gateway", message)),
+            },
+            Err(message) => errors.push(FieldError::new("gateway", message)),
+        }
+    } else {
+        normalized.gateway = String::new();
+    }
+
+    normalized.description = host.description.trim().to_string();
+    normalized.os = host.os.trim().to_string();
+
+    // Custom field names are trimmed, empty values dropped
+    normalized.fields = BTreeMap::new();
+    for (key, value) in &host.fields {
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() || value.is_empty() {
+            continue;
+        }
+        if key.contains(['=', ';']) || value.contains(';') {
+            errors.push(FieldError::new("fields", format!("'{}={}' cannot contain ';', or '=' in the name", key, value)));
+        } else {
+            normalized.fields.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    // Aliases have to be valid names on their own, without a port
+    normalized.aliases = Vec::new();
+    for alias in &host.aliases {
+        let alias = alias.trim();
+        if alias.is_empty() {
+            continue;
+        }
+        match validate_hostname(alias) {
+            Ok(alias) if alias.eq_ignore_ascii_case(&normalized.hostname) => {}
+            Ok(alias) => {
+                if !normalized.aliases.iter().any(|a| a.eq_ignore_ascii_case(&alias)) {
+                    normalized.aliases.push(alias);
+                }
+            }
+            Err(message) => errors.push(FieldError::new("aliases", format!("Alias '{}': {}", alias, message))),
+        }
+    }
+
+    // Tags are compared ignoring case, the first spelling is kept
+    normalized.tags = Vec::new();
+    for tag in &host.tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.contains(';') {
+            errors.push(FieldError::new("tags", format!("Tag '{}' cannot contain ';'", tag)));
+        } else if !normalized.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.tags.push(tag.to_string());
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(normalized)
+    } else {
+        Err(errors)
+    }
+}
+
+// A name the host shares with one of `others`: an alias that is another
+// host's hostname or alias, or a hostname another host uses as an alias.
+// Searching or connecting by that name would be ambiguous. Hostnames equal
+// to each other are duplicates, which callers deal with on their own.
+pub fn name_conflict<'a>(host: &Host, others: impl IntoIterator<Item = &'a Host>) -> Option<String> {
+    for other in others {
+        let other_alias = |name: &str| other.aliases.iter().any(|a| a.eq_ignore_ascii_case(name));
+        for alias in &host.aliases {
+            if other.hostname.eq_ignore_ascii_case(alias) {
+                return Some(format!("Alias {} of {} is the hostname of another host", alias, host.hostname));
+            }
+            if other_alias(alias) {
+                return Some(format!("Alias {} of {} is already an alias of {}", alias, host.hostname, other.hostname));
+            }
+        }
+        if other_alias(&host.hostname) {
+            return Some(format!("{} is already an alias of {}", host.hostname, other.hostname));
+        }
+    }
+    None
+}
+
+// Every name conflict in a list of hosts, one message per host that clashes
+// with a host before it
+pub fn name_conflicts(hosts: &[Host]) -> Vec<String> {
+    hosts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, host)| name_conflict(host, &hosts[..i]))
+        .collect()
+}
+
+// The reverse of parse_address: IPv6 literals need brackets when a port follows
+pub fn format_address(host: &str, port: Option<u16>) -> String {
+    match port {
+        Some(port) if host.parse::<Ipv6Addr>().is_ok() => format!("[{}]:{}", host, port),
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+// Lets the hosts window show errors next to the fields before saving
+#[tauri::command]
+pub fn validate_host(host: Host) -> Result<Vec<FieldError>, String> {
+    Ok(normalize_host(&host).err().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{host, strings};
+
+    fn with_aliases(hostname: &str, aliases: &[&str]) -> Host {
+        Host { aliases: strings(aliases), ..host(hostname) }
+    }
+
+    #[test]
+    fn finds_names_shared_between_hosts() {
+        let sql = with_aliases("lon-sql01", &["sqlcluster"]);
+        assert!(name_conflict(&with_aliases("ams-web01", &["lon-sql01"]), [&sql]).is_some());
+        assert!(name_conflict(&with_aliases("ams-web01", &["SQLCluster"]), [&sql]).is_some());
+        assert!(name_conflict(&host("sqlcluster"), [&sql]).is_some());
+        assert!(name_conflict(&with_aliases("ams-web01", &["web"]), [&sql]).is_none());
+        // Duplicate hostnames are left to the callers
+        assert!(name_conflict(&host("lon-sql01"), [&sql]).is_none());
+    }
+
+    #[test]
+    fn parses_addresses_with_and_without_ports() {
+        let parsed = |input: &str| parse_address(input).unwrap();
+        assert_eq!(parsed("srv01"), ("srv01".to_string(), None));
+        assert_eq!(parsed(" srv01:3390 "), ("srv01".to_string(), Some(3390)));
+        assert_eq!(parsed("[fe80::1]:3390"), ("fe80::1".to_string(), Some(3390)));
+        assert_eq!(parsed("[fe80::1]"), ("fe80::1".to_string(), None));
+        assert_eq!(parsed("fe80::1"), ("fe80::1".to_string(), None));
+
+        assert!(parse_address("srv01:0").is_err());
+        assert!(parse_address("srv01:65536").is_err());
+        assert!(parse_address("srv01:rdp").is_err());
+        assert!(parse_address("[fe80::1").is_err());
+        assert!(parse_address("[fe80::1]3390").is_err());
+        assert!(parse_address("fe80::1:3390:x").is_err());
+    }
+
+    #[test]
+    fn validates_hostnames() {
+        assert_eq!(validate_hostname("lon-sql01.corp.local").unwrap(), "lon-sql01.corp.local");
+        // A trailing dot marks a fully qualified name and is dropped
+        assert_eq!(validate_hostname("lon-sql01.corp.local.").unwrap(), "lon-sql01.corp.local");
+        assert_eq!(validate_hostname("bücher.example").unwrap(), "xn--bcher-kva.example");
+        assert_eq!(validate_hostname("10.0.0.1").unwrap(), "10.0.0.1");
+        assert_eq!(validate_hostname("FE80:0::1").unwrap(), "fe80::1");
+
+        assert!(validate_hostname("10.0.0.300").is_err());
+        assert!(validate_hostname("").is_err());
+        assert!(validate_hostname("srv..local").is_err());
+        assert!(validate_hostname("srv01.local..").is_err());
+        assert!(validate_hostname("-srv01").is_err());
+        assert!(validate_hostname("srv01-.local").is_err());
+        assert!(validate_hostname("srv_01").is_err());
+
+        let label = "a".repeat(MAX_LABEL_LENGTH);
+        assert!(validate_hostname(&label).is_ok());
+        assert!(validate_hostname(&format!("{}a", label)).is_err());
+        let name = [label.as_str(); 4].join(".");
+        assert_eq!(name.len(), 255);
+        assert!(validate_hostname(&name).is_err());
+        assert!(validate_hostname(&name[..MAX_HOSTNAME_LENGTH]).is_ok());
+    }
+
+    #[test]
+    fn normalizes_hosts() {
+        let typed = Host {
+            description: "  SQL node ".to_string(),
+            gateway: "RDGW.example.com:443".to_string(),
+            aliases: strings(&[" sqlcluster ", "SQLCLUSTER", "srv01", ""]),
+            tags: strings(&["prod", " Prod", ""]),
+            fields: BTreeMap::from([(" role ".to_string(), " SQL ".to_string()), ("site".to_string(), " ".to_string())]),
+            ..host("srv01:3390")
+        };
+
+        let normalized = normalize_host(&typed).unwrap();
+        assert_eq!(normalized.hostname, "srv01");
+        assert_eq!(normalized.port, Some(3390));
+        assert_eq!(normalized.description, "SQL node");
+        assert_eq!(normalized.gateway, "RDGW.example.com:443");
+        assert_eq!(normalized.aliases, strings(&["sqlcluster"]));
+        assert_eq!(normalized.tags, strings(&["prod"]));
+        assert_eq!(normalized.fields, BTreeMap::from([("role".to_string(), "SQL".to_string())]));
+    }
+
+    #[test]
+    fn reports_every_invalid_field() {
+        let invalid = Host {
+            port: Some(3389),
+            gateway: "rdgw example".to_string(),
+            aliases: strings(&["bad alias"]),
+            tags: strings(&["a;b"]),
+            ..host("srv01:3390")
+        };
+
+        let errors = normalize_host(&invalid).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["port", "gateway", "aliases", "tags"]);
+        assert!(normalize_host(&Host { port: Some(0), ..host("srv01") }).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{diff, inventory, log_to_file, search_index, Host, HOSTS};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+use tauri::Emitter;
+
+// Excel and most scripts write the file in several steps, so wait for things to settle
+const DEBOUNCE: Duration = Duration::from_millis(750);
+
+pub fn watch_hosts_file(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let (tx, rx) = mpsc::channel::<DebounceEventResult>();
+
+        let mut debouncer = match new_debouncer(DEBOUNCE, tx) {
+            Ok(debouncer) => debouncer,
+            Err(e) => {
+                log_to_file(&format!("Failed to create hosts file watcher: {}", e));
+                return;
+            }
+        };
+
+        // Watch the directory rather than the file itself: editors that save by
+        // replacing the file would otherwise leave us watching a deleted inode
+        let watch_dir = match std::env::current_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                log_to_file(&format!("Failed to resolve hosts file directory: {}", e));
+                return;
+            }
+        };
+
+        if let Err(e) = debouncer.watcher().watch(&watch_dir, RecursiveMode::NonRecursive) {
+            log_to_file(&format!("Failed to watch {}: {}", watch_dir.display(), e));
+            return;
+        }
+
+        for result in rx {
+            match result {
+                Ok(events) => {
+                    let inventory = inventory::path();
+                    if events.iter().any(|event| is_hosts_file(&event.path, &inventory)) {
+                        reload_hosts(&app_handle);
+                    }
+                }
+                Err(e) => log_to_file(&format!("Hosts file watcher error: {}", e)),
+            }
+        }
+    });
+}
+
+fn is_hosts_file(path: &Path, inventory: &Path) -> bool {
+    match (path.file_name(), inventory.file_name()) {
+        (Some(name), Some(inventory_name)) => name.eq_ignore_ascii_case(inventory_name),
+        _ => false,
+    }
+}
+
+fn reload_hosts(app_handle: &tauri::AppHandle) {
+    let path = inventory::path();
+    if !path.exists() {
+        // Replaced by a rename, the new file shows up as its own event
+        return;
+    }
+
+    // Invalid rows are left out rather than rejecting the whole edit
+    let report = match inventory::load(&path) {
+        Ok(report) => report,
+        Err(e) => {
+            log_to_file(&format!("Ignoring {} change, failed to read: {}", path.display(), e));
+            return;
+        }
+    };
+    if !report.problems.is_empty() {
+        log_to_file(&format!("Skipped {} invalid row(s) while reloading {}", report.problems.len(), path.display()));
+    }
+    let loaded = report.hosts;
+
+    let changes = match HOSTS.lock() {
+        Ok(mut hosts_data) => {
+            search_index::update(&loaded);
+            replace_cached(&mut hosts_data, loaded)
+        }
+        Err(_) => {
+            log_to_file("Failed to acquire hosts lock while reloading the inventory");
+            return;
+        }
+    };
+
+    if changes.is_empty() {
+        return;
+    }
+
+    log_to_file(&format!(
+        "Reloaded {}: {} added, {} removed, {} modified",
+        path.display(),
+        changes.added.len(),
+        changes.removed.len(),
+        changes.modified.len()
+    ));
+
+    if let Err(e) = app_handle.emit("hosts-changed", changes) {
+        log_to_file(&format!("Failed to emit hosts-changed event: {}", e));
+    }
+}
+
+// Swap in the hosts read from disk and return what changed. Saves made by the
+// app already updated the cache, so the event they trigger comes back empty.
+fn replace_cached(cached: &mut Vec<Host>, loaded: Vec<Host>) -> diff::HostsDiff {
+    let changes = diff::diff_hosts(cached, &loaded);
+    *cached = loaded;
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::host;
+
+    #[test]
+    fn only_the_inventory_file_triggers_a_reload() {
+        let inventory = Path::new("/data/hosts.csv");
+        assert!(is_hosts_file(Path::new("/data/hosts.csv"), inventory));
+        assert!(is_hosts_file(Path::new("/data/HOSTS.CSV"), inventory));
+        assert!(!is_hosts_file(Path::new("/data/hosts.csv.tmp"), inventory));
+        assert!(!is_hosts_file(Path::new("/data/recent.json"), inventory));
+    }
+
+    #[test]
+    fn reloading_what_was_saved_reports_nothing() {
+        let saved = vec![host("srv01"), host("srv02")];
+        let mut cached = saved.clone();
+        assert!(replace_cached(&mut cached, saved.clone()).is_empty());
+        assert_eq!(cached, saved);
+    }
+
+    #[test]
+    fn reports_external_edits_once() {
+        let mut cached = vec![host("srv01"), host("srv02")];
+        let edited = vec![Host { port: Some(3390), ..host("srv01") }, host("srv03")];
+
+        let changes = replace_cached(&mut cached, edited.clone());
+        assert_eq!(changes.added, vec!["srv03"]);
+        assert_eq!(changes.removed, vec!["srv02"]);
+        assert_eq!(changes.modified, vec!["srv01"]);
+
+        // The debouncer can still deliver a second event for the same write
+        assert!(replace_cached(&mut cached, edited).is_empty());
+    }
+}
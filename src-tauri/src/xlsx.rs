@@ -0,0 +1,303 @@
+use crate::csv_loader::{
+    join_fields, join_rdp, parse_flag, split_fields, split_rdp, split_tags, ColumnMap, HOSTS_CSV_HEADER,
+};
+use crate::importers;
+use crate::staging::StagedChanges;
+use crate::Host;
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Header names of the columns holding each Host field. Only the hostname is
+// required, fields without a column are left empty.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct XlsxColumnMapping {
+    pub hostname: String,
+    pub description: Option<String>,
+    pub group: Option<String>,
+    pub port: Option<String>,
+    pub gateway: Option<String>,
+    pub tags: Option<String>,
+    pub os: Option<String>,
+    pub aliases: Option<String>,
+    pub favorite: Option<String>,
+    pub fields: Option<String>,
+    pub rdp: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SheetInfo {
+    name: String,
+    headers: Vec<String>,
+    rows: usize,
+    // Mapping guessed from the header names, None if no column looks like a hostname
+    suggested_mapping: Option<XlsxColumnMapping>,
+}
+
+fn read_sheet(path: &Path, sheet: &str) -> Result<Range<Data>, String> {
+    let mut workbook = open_workbook_auto(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    workbook
+        .worksheet_range(sheet)
+        .map_err(|e| format!("Failed to read sheet {}: {}", sheet, e))
+}
+
+fn cell_text(cell: Option<&Data>) -> String {
+    cell.map(|c| c.to_string().trim().to_string()).unwrap_or_default()
+}
+
+// The first row of the used range holds the headers
+fn headers(range: &Range<Data>) -> Vec<String> {
+    range
+        .rows()
+        .next()
+        .map(|row| row.iter().map(|c| cell_text(Some(c))).collect())
+        .unwrap_or_default()
+}
+
+fn suggest_mapping(headers: &[String]) -> Option<XlsxColumnMapping> {
+    let columns = ColumnMap::from_headers(headers.iter().map(String::as_str))?;
+    let name = |idx: Option<usize>| idx.map(|i| headers[i].clone());
+    Some(XlsxColumnMapping {
+        hostname: headers[columns.hostname].clone(),
+        description: name(columns.description),
+        group: name(columns.group),
+        port: name(columns.port),
+        gateway: name(columns.gateway),
+        tags: name(columns.tags),
+        os: name(columns.os),
+        aliases: name(columns.aliases),
+        favorite: name(columns.favorite),
+        fields: name(columns.fields),
+        rdp: name(columns.rdp),
+    })
+}
+
+// Lists the sheets of a workbook with their headers, so the user can pick a
+// sheet and check the column mapping before importing
+#[tauri::command]
+pub fn list_xlsx_sheets(path: String) -> Result<Vec<SheetInfo>, String> {
+    let path = Path::new(&path);
+    let mut workbook = open_workbook_auto(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let mut sheets = Vec::new();
+    for name in workbook.sheet_names() {
+        let range = workbook
+            .worksheet_range(&name)
+            .map_err(|e| format!("Failed to read sheet {}: {}", name, e))?;
+        let headers = headers(&range);
+        sheets.push(SheetInfo {
+            suggested_mapping: suggest_mapping(&headers),
+            rows: range.height().saturating_sub(1),
+            name,
+            headers,
+        });
+    }
+    Ok(sheets)
+}
+
+// Stage hosts from a spreadsheet for import. Without a sheet the first one is
+// used, and without a mapping the columns are matched by header name like hosts.csv.
+#[tauri::command]
+pub fn import_xlsx(
+    path: String,
+    sheet: Option<String>,
+    mapping: Option<XlsxColumnMapping>,
+) -> Result<StagedChanges, String> {
+    let path = Path::new(&path);
+    let sheet = match sheet {
+        Some(sheet) => sheet,
+        None => {
+            let workbook = open_workbook_auto(path)
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            workbook
+                .sheet_names()
+                .into_iter()
+                .next()
+                .ok_or(format!("{} has no sheets", path.display()))?
+        }
+    };
+
+    let range = read_sheet(path, &sheet)?;
+    let (parsed, skipped) = read_hosts(&range, &sheet, mapping)?;
+    importers::merge_import(path, parsed, skipped)
+}
+
+// Hosts from every row below the header, and a message for each row skipped
+fn read_hosts(
+    range: &Range<Data>,
+    sheet: &str,
+    mapping: Option<XlsxColumnMapping>,
+) -> Result<(Vec<Host>, Vec<String>), String> {
+    let headers = headers(range);
+    let mapping = match mapping {
+        Some(mapping) => mapping,
+        None => suggest_mapping(&headers)
+            .ok_or(format!("No hostname column found in sheet {}, choose one to import", sheet))?,
+    };
+
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name.trim()))
+            .ok_or(format!("Column '{}' not found in sheet {}", name, sheet))
+    };
+    let optional_column = |name: &Option<String>| match name.as_deref() {
+        Some(name) if !name.trim().is_empty() => column(name).map(Some),
+        _ => Ok(None),
+    };
+
+    let hostname_col = column(&mapping.hostname)?;
+    let description_col = optional_column(&mapping.description)?;
+    let group_col = optional_column(&mapping.group)?;
+    let port_col = optional_column(&mapping.port)?;
+    let gateway_col = optional_column(&mapping.gateway)?;
+    let tags_col = optional_column(&mapping.tags)?;
+    let os_col = optional_column(&mapping.os)?;
+    let aliases_col = optional_column(&mapping.aliases)?;
+    let favorite_col = optional_column(&mapping.favorite)?;
+    let fields_col = optional_column(&mapping.fields)?;
+    let rdp_col = optional_column(&mapping.rdp)?;
+
+    // Row numbers as Excel shows them, the range may not start at A1
+    let first_row = range.start().map(|(row, _)| row as usize + 1).unwrap_or(1);
+
+    let mut parsed = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, row) in range.rows().enumerate().skip(1) {
+        let field = |idx: Option<usize>| cell_text(idx.and_then(|i| row.get(i)));
+        let row_number = first_row + index;
+
+        let hostname = field(Some(hostname_col));
+        if hostname.is_empty() {
+            if row.iter().any(|c| !cell_text(Some(c)).is_empty()) {
+                skipped.push(format!("Row {}: no hostname", row_number));
+            }
+            continue;
+        }
+
+        let port = match field(port_col).as_str() {
+            "" => None,
+            value => match value.parse::<u16>() {
+                Ok(port) => Some(port),
+                Err(_) => {
+                    skipped.push(format!("Row {}: invalid port '{}'", row_number, value));
+                    continue;
+                }
+            },
+        };
+
+        let rdp = match split_rdp(&field(rdp_col)) {
+            Ok(rdp) => rdp,
+            Err(e) => {
+                skipped.push(format!("Row {}: {}", row_number, e));
+                continue;
+            }
+        };
+
+        parsed.push(Host {
+            hostname,
+            description: field(description_col),
+            group: field(group_col),
+            port,
+            gateway: field(gateway_col),
+            aliases: split_tags(&field(aliases_col)),
+            os: field(os_col),
+            tags: split_tags(&field(tags_col)),
+            fields: split_fields(&field(fields_col)),
+            favorite: parse_flag(&field(favorite_col)),
+            rdp,
+        });
+    }
+
+    Ok((parsed, skipped))
+}
+
+// One row per host with the same columns as hosts.csv, plus the username
+// when credentials are exported
+pub fn write_xlsx(path: &Path, hosts: &[Host], usernames: &HashMap<String, String>) -> Result<(), String> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet
+        .set_name("Hosts")
+        .map_err(|e| format!("Failed to create worksheet: {}", e))?;
+
+    let mut columns = HOSTS_CSV_HEADER.to_vec();
+    if !usernames.is_empty() {
+        columns.push("username");
+    }
+
+    let bold = Format::new().set_bold();
+    for (col, name) in columns.iter().enumerate() {
+        worksheet
+            .write_string_with_format(0, col as u16, *name, &bold)
+            .map_err(|e| format!("Failed to write header: {}", e))?;
+    }
+
+    for (index, host) in hosts.iter().enumerate() {
+        let row = index as u32 + 1;
+        let mut values = vec![
+            host.hostname.clone(),
+            host.description.clone(),
+            host.group.clone(),
+            String::new(),
+            host.gateway.clone(),
+            host.tags.join("; "),
+            join_fields(&host.fields),
+            host.os.clone(),
+            host.aliases.join("; "),
+            if host.favorite { "yes" } else { "" }.to_string(),
+            join_rdp(&host.rdp),
+        ];
+        if !usernames.is_empty() {
+            values.push(usernames.get(&host.hostname).cloned().unwrap_or_default());
+        }
+
+        for (col, value) in values.iter().enumerate().filter(|(_, value)| !value.is_empty()) {
+            worksheet
+                .write_string(row, col as u16, value)
+                .map_err(|e| format!("Failed to write {}: {}", host.hostname, e))?;
+        }
+        // Ports go in as numbers so Excel doesn't flag them as text
+        if let Some(port) = host.port {
+            worksheet
+                .write_number(row, 3, port as f64)
+                .map_err(|e| format!("Failed to write {}: {}", host.hostname, e))?;
+        }
+    }
+
+    worksheet
+        .set_freeze_panes(1, 0)
+        .map_err(|e| format!("Failed to freeze header row: {}", e))?;
+    worksheet
+        .autofilter(0, 0, hosts.len() as u32, columns.len() as u16 - 1)
+        .map_err(|e| format!("Failed to add filter: {}", e))?;
+    worksheet.autofit();
+
+    let contents = workbook
+        .save_to_buffer()
+        .map_err(|e| format!("Failed to build workbook: {}", e))?;
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{full_host, host, temp_path};
+
+    #[test]
+    fn exported_hosts_import_unchanged() {
+        let hosts = vec![full_host(), host("ams-web01")];
+        let path = temp_path("round-trip.xlsx");
+        write_xlsx(&path, &hosts, &HashMap::new()).unwrap();
+        let range = read_sheet(&path, "Hosts");
+        std::fs::remove_file(&path).unwrap();
+
+        let (parsed, skipped) = read_hosts(&range.unwrap(), "Hosts", None).unwrap();
+        assert!(skipped.is_empty(), "{:?}", skipped);
+        assert_eq!(parsed, hosts);
+    }
+}
@@ -1,8 +1,49 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 interface Host {
   hostname: string;
   description: string;
+  group?: string;
+  port?: number | null;
+  gateway?: string;
+  aliases?: string[];
+  os?: string;
+  tags?: string[];
+  fields?: Record<string, string>;
+  favorite?: boolean;
+  rdp?: Record<string, boolean | number | null>;
+}
+
+interface FieldError {
+  field: string;
+  message: string;
+}
+
+interface HostsFileReport {
+  path: string;
+  valid_hosts: number;
+  problems: { line: number; reason: string }[];
+}
+
+type Resolution = "keep_local" | "take_incoming" | "merge";
+
+interface HostConflict {
+  hostname: string;
+  local: Host;
+  incoming: Host;
+  merged: Host;
+  fields: string[];
+}
+
+interface StagedChanges {
+  id: string;
+  source: string;
+  added: Host[];
+  removed: Host[];
+  conflicts: HostConflict[];
+  unchanged: number;
+  skipped: string[];
 }
 
 interface StoredCredentials {
@@ -12,10 +53,16 @@ interface StoredCredentials {
 
 let hosts: Host[] = [];
 let filteredHosts: Host[] = [];
+// Hostname of the host open in the edit form, null when adding one
+let editingHostname: string | null = null;
 
 document.addEventListener("DOMContentLoaded", () => {
   loadHosts();
   setupEventListeners();
+  // Reload when hosts.csv is edited outside ConnectX
+  listen("hosts-changed", () => {
+    loadHosts();
+  });
   window.addEventListener("keydown", async (e) => {
     if (e.key === "Escape") {
       try {
@@ -33,6 +80,7 @@ function setupEventListeners() {
     document.getElementById("modalTitle")!.textContent = "Add Host";
     const form = document.getElementById("hostForm") as HTMLFormElement;
     form.reset();
+    editingHostname = null;
     modal.showModal();
   });
 
@@ -42,18 +90,22 @@ function setupEventListeners() {
     const hostnameInput = document.getElementById("hostname") as HTMLInputElement;
     const hostname = hostnameInput.value.trim();
     
-    if (!isValidFQDN(hostname)) {
-      alert("Please enter a valid hostname in the format: server.domain.com");
-      return;
-    }
-    
+    // Keep the fields the form doesn't edit (group, port, gateway) when updating a host
+    const existing = hosts.find(h => h.hostname === (editingHostname ?? hostname));
     const host: Host = {
+      ...existing,
       hostname: hostname,
       description: (document.getElementById("description") as HTMLTextAreaElement).value,
     };
     
     try {
-      await saveHost(host);
+      const errors = await invoke<FieldError[]>("validate_host", { host });
+      if (errors.length > 0) {
+        showToast(errors.map(e => `${e.field}: ${e.message}`), 'error');
+        return;
+      }
+
+      await saveHost(host, editingHostname);
       (document.getElementById("hostModal") as HTMLDialogElement).close();
     } catch (error) {
       console.error("Failed to save host:", error);
@@ -127,11 +179,10 @@ function setupEventListeners() {
         <span class="ml-2">Scanning...</span>
       `;
       
-      const result = await invoke<string>("scan_domain", { domain, server });
+      const changes = await invoke<StagedChanges>("scan_domain", { domain, server });
       
       modal.close();
-      showToast(result, 'success');
-      await loadHosts();
+      showStagedChanges(changes);
       
     } catch (error) {
       console.error("Failed to scan domain:", error);
@@ -145,6 +196,16 @@ function setupEventListeners() {
 
   document.getElementById("deleteAllHosts")?.addEventListener("click", deleteAllHosts);
 
+  document.getElementById("applyChanges")?.addEventListener("click", applyStagedChanges);
+  document.getElementById("discardChanges")?.addEventListener("click", async () => {
+    try {
+      await invoke("discard_staged_changes");
+    } catch (error) {
+      console.error("Failed to discard changes:", error);
+    }
+    (document.getElementById("reviewChangesModal") as HTMLDialogElement).close();
+  });
+
   // Add search input event listener
   document.getElementById("hostSearch")?.addEventListener("input", (e) => {
     const searchTerm = (e.target as HTMLInputElement).value.toLowerCase();
@@ -169,11 +230,27 @@ async function loadHosts() {
     hosts = await invoke<Host[]>("get_hosts");
     filteredHosts = [...hosts];
     renderHosts();
+    await reportHostsFileProblems();
   } catch (error) {
     console.error("Failed to load hosts:", error);
   }
 }
 
+// Rows that couldn't be loaded are skipped, let the user know which ones
+async function reportHostsFileProblems() {
+  const report = await invoke<HostsFileReport>("validate_hosts_file");
+  if (report.problems.length === 0) return;
+
+  const lines = [
+    `Skipped ${report.problems.length} row(s) in ${report.path}:`,
+    ...report.problems.slice(0, 5).map(p => `Line ${p.line}: ${p.reason}`),
+  ];
+  if (report.problems.length > 5) {
+    lines.push(`...and ${report.problems.length - 5} more`);
+  }
+  showToast(lines, 'error');
+}
+
 function renderHosts() {
   const tbody = document.querySelector('#hostsTable tbody')!;
   const noHostsMessage = document.getElementById('noHostsMessage')!;
@@ -216,9 +293,9 @@ function renderHosts() {
   }
 }
 
-async function saveHost(host: Host) {
+async function saveHost(host: Host, previousHostname: string | null = null) {
   try {
-    await invoke("save_host", { host });
+    await invoke("save_host", { host, previousHostname });
     await loadHosts();
   } catch (error) {
     console.error("Failed to save host:", error);
@@ -246,23 +323,12 @@ window.editHost = (hostname: string) => {
   
   const form = document.getElementById("hostForm") as HTMLFormElement;
   (form.querySelector("#hostname") as HTMLInputElement).value = host.hostname;
+  editingHostname = host.hostname;
   (form.querySelector("#description") as HTMLTextAreaElement).value = host.description;
   
   modal.showModal();
 };
 
-function isValidFQDN(hostname: string): boolean {
-  // This regex validates FQDN format:
-  // - Contains at least one dot
-  // - Only allows letters, numbers, dots, and hyphens
-  // - Doesn't allow consecutive dots
-  // - Doesn't start or end with a dot or hyphen
-  // - Labels are 1-63 characters long
-  // - Total length is 1-253 characters
-  const fqdnRegex = /^(?!-)[A-Za-z0-9-]{1,63}(?<!-)(\.[A-Za-z0-9-]{1,63})*\.[A-Za-z]{2,}$/;
-  return fqdnRegex.test(hostname) && hostname.length <= 253;
-}
-
 function isValidDomain(domain: string): boolean {
   // Basic domain validation: letters, numbers, dots, hyphens
   const domainRegex = /^[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9](\.[a-zA-Z]{2,})+$/;
@@ -286,13 +352,105 @@ function isValidServerName(server: string, domain: string): boolean {
   return hostnameRegex.test(prefix);
 }
 
-function showToast(message: string, type: 'success' | 'error' = 'success') {
+let stagedChanges: StagedChanges | null = null;
+
+// Every bulk import is staged first; show what it would change and let the
+// user pick what happens to each host that differs or would be removed
+function showStagedChanges(changes: StagedChanges) {
+  stagedChanges = changes;
+  const modal = document.getElementById("reviewChangesModal") as HTMLDialogElement;
+  document.getElementById("reviewSource")!.textContent = changes.source;
+  document.getElementById("reviewSummary")!.textContent =
+    `${changes.added.length} added, ${changes.removed.length} removed, ` +
+    `${changes.conflicts.length} changed, ${changes.unchanged} unchanged` +
+    (changes.skipped.length > 0 ? `, ${changes.skipped.length} skipped` : "");
+
+  const list = document.getElementById("reviewList")!;
+  list.replaceChildren();
+
+  const addRow = (hostname: string, detail: string, options: [Resolution, string][]) => {
+    const row = document.createElement("div");
+    row.className = "flex items-center justify-between gap-4 bg-base-200 rounded-lg px-4 py-2";
+    const text = document.createElement("div");
+    const name = document.createElement("div");
+    name.className = "font-semibold";
+    name.textContent = hostname;
+    const info = document.createElement("div");
+    info.className = "text-sm opacity-70";
+    info.textContent = detail;
+    text.append(name, info);
+    row.appendChild(text);
+
+    if (options.length > 0) {
+      const select = document.createElement("select");
+      select.className = "select select-bordered select-sm";
+      select.dataset.hostname = hostname;
+      for (const [value, label] of options) {
+        const option = document.createElement("option");
+        option.value = value;
+        option.textContent = label;
+        select.appendChild(option);
+      }
+      row.appendChild(select);
+    }
+    list.appendChild(row);
+  };
+
+  for (const conflict of changes.conflicts) {
+    addRow(conflict.hostname, `Changed: ${conflict.fields.join(", ")}`, [
+      ["take_incoming", "Take incoming"],
+      ["keep_local", "Keep local"],
+      ["merge", "Merge"],
+    ]);
+  }
+  for (const host of changes.removed) {
+    addRow(host.hostname, "Not in the incoming hosts", [
+      ["take_incoming", "Remove"],
+      ["keep_local", "Keep"],
+    ]);
+  }
+  for (const host of changes.added) {
+    addRow(host.hostname, "New host", []);
+  }
+  for (const reason of changes.skipped) {
+    addRow("Skipped", reason, []);
+  }
+
+  modal.showModal();
+}
+
+async function applyStagedChanges() {
+  if (!stagedChanges) return;
+
+  const resolutions: Record<string, Resolution> = {};
+  document.querySelectorAll<HTMLSelectElement>("#reviewList select").forEach(select => {
+    resolutions[select.dataset.hostname!] = select.value as Resolution;
+  });
+
+  try {
+    const result = await invoke<string>("apply_staged_changes", { id: stagedChanges.id, resolutions });
+    (document.getElementById("reviewChangesModal") as HTMLDialogElement).close();
+    stagedChanges = null;
+    showToast(result, 'success');
+    await loadHosts();
+  } catch (error) {
+    console.error("Failed to apply changes:", error);
+    showToast(`Failed to apply changes: ${error}`, 'error');
+  }
+}
+
+// Messages often quote hostnames or rows from the hosts file, so they are
+// added as text, one line per entry, never parsed as HTML
+function showToast(message: string | string[], type: 'success' | 'error' = 'success') {
   const toastContainer = document.getElementById('toastContainer')!;
   const toast = document.createElement('div');
   toast.className = `alert ${type === 'success' ? 'alert-success' : 'alert-error'} mb-2`;
-  toast.innerHTML = `
-    <span>${message}</span>
-  `;
+  const span = document.createElement('span');
+  (Array.isArray(message) ? message : [message]).forEach((line, i) => {
+    if (i > 0) span.appendChild(document.createElement('br'));
+    span.appendChild(document.createTextNode(line));
+  });
+  toast.appendChild(span);
   toastContainer.appendChild(toast);
   
   // Remove toast after 5 seconds
@@ -365,7 +523,7 @@ window.saveHostCredentials = async (hostname: string) => {
 };
 
 async function deleteAllHosts() {
-  if (!confirm("Are you sure you want to delete all hosts? This action cannot be undone.")) return;
+  if (!confirm("Are you sure you want to delete all hosts? They can be restored from the deleted hosts for 30 days.")) return;
   
   try {
     await invoke("delete_all_hosts");
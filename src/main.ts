@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 interface StoredCredentials {
   username: string;
@@ -8,6 +9,15 @@ interface StoredCredentials {
 interface Host {
     hostname: string;
     description: string;
+    group?: string;
+    port?: number | null;
+    gateway?: string;
+    aliases?: string[];
+    os?: string;
+    tags?: string[];
+    fields?: Record<string, string>;
+    favorite?: boolean;
+    rdp?: Record<string, boolean | number | null>;
 }
 
 function showNotification(message: string, isError: boolean = false) {
@@ -129,6 +139,139 @@ function initializeTheme() {
 // Declare this once at the top of the file
 let searchTimeout: number;
 
+// Only the best matches are shown, large inventories can match thousands of hosts
+const SEARCH_PAGE_SIZE = 50;
+
+interface MatchHighlight {
+    field: string;
+    key?: string;
+    // [start, end) offsets into the field's text
+    ranges: [number, number][];
+}
+
+interface SearchResult extends Host {
+    // Seconds since the epoch
+    last_connected?: number;
+    matches: MatchHighlight[];
+}
+
+// The text of a result field with the parts that matched the search marked
+function highlightField(text: string, result: SearchResult, field: string, key?: string): DocumentFragment {
+    const fragment = document.createDocumentFragment();
+    const ranges = result.matches.find(m => m.field === field && m.key === key)?.ranges ?? [];
+
+    let position = 0;
+    for (const [start, end] of ranges) {
+        fragment.append(text.slice(position, start));
+        const mark = document.createElement("mark");
+        mark.className = "bg-primary/20 text-inherit rounded-sm";
+        mark.textContent = text.slice(start, end);
+        fragment.append(mark);
+        position = end;
+    }
+    fragment.append(text.slice(position));
+    return fragment;
+}
+
+interface SearchPage {
+    total: number;
+    results: SearchResult[];
+}
+
+// One row of the server list, used for search results and favorites alike
+function renderHost(host: SearchResult): HTMLElement {
+    const item = document.createElement("div");
+    item.className = "flex items-center justify-between p-4 border-b border-base-300 last:border-b-0";
+
+    item.innerHTML = `
+        <div class="flex flex-col">
+            <span class="host-name font-medium"></span>
+            <span class="host-description text-sm opacity-70"></span>
+            <span class="host-aliases text-xs opacity-70"></span>
+            ${host.last_connected ? `<span class="text-xs opacity-50">Last connected ${new Date(host.last_connected * 1000).toLocaleString()}</span>` : ""}
+        </div>
+        <div class="flex items-center gap-1">
+            <button class="favorite-btn btn btn-ghost btn-sm" title="${host.favorite ? "Remove from favorites" : "Add to favorites"}">
+                ${host.favorite ? "★" : "☆"}
+            </button>
+            <button class="connect-btn btn btn-primary btn-sm">
+                Connect
+            </button>
+        </div>
+    `;
+
+    item.querySelector(".host-name")?.append(highlightField(host.hostname, host, "hostname"));
+    item.querySelector(".host-description")?.append(highlightField(host.description, host, "description"));
+
+    // Clicking an alias connects by that name instead of the hostname
+    const aliases = item.querySelector(".host-aliases");
+    if (aliases && host.aliases?.length) {
+        aliases.append("Also known as ");
+        host.aliases.forEach((alias, i) => {
+            if (i > 0) aliases.append(", ");
+            const link = document.createElement("a");
+            link.className = "link link-hover";
+            link.title = `Connect to ${alias}`;
+            link.append(highlightField(alias, host, "aliases", alias));
+            link.addEventListener("click", async (e) => {
+                e.stopPropagation();
+                try {
+                    await invoke("launch_rdp", { host, target: { alias } });
+                } catch (err) {
+                    console.error("Failed to connect:", err);
+                }
+            });
+            aliases.appendChild(link);
+        });
+    }
+
+    item.querySelector(".favorite-btn")?.addEventListener("click", async (e) => {
+        e.stopPropagation();
+        try {
+            await invoke("set_favorite", { hostname: host.hostname, favorite: !host.favorite });
+            handleSearch();
+        } catch (err) {
+            console.error("Failed to update favorite:", err);
+            showNotification(typeof err === "string" ? err : "Failed to update favorite", true);
+        }
+    });
+
+    // Add click handler for the connect button
+    const connectBtn = item.querySelector('.connect-btn');
+    if (connectBtn) {
+        connectBtn.addEventListener('click', async (e) => {
+            e.stopPropagation();
+            try {
+                await invoke("launch_rdp", { host });
+            } catch (err) {
+                console.error("Failed to connect:", err);
+            }
+        });
+    }
+
+    return item;
+}
+
+// Shown while the search box is empty
+async function showFavorites(serverList: HTMLElement) {
+    const favorites = await invoke<Host[]>("get_favorites");
+    if (favorites.length === 0) {
+        serverList.innerHTML = `
+            <div class="text-center text-base-content/60 p-4">
+                Search for servers to connect
+            </div>
+        `;
+        return;
+    }
+
+    serverList.innerHTML = `
+        <div class="text-xs uppercase tracking-wide text-base-content/60 px-4 pt-3 pb-1">
+            Favorites
+        </div>
+    `;
+    favorites.forEach(host => serverList.appendChild(renderHost({ ...host, matches: [] })));
+}
+
 async function handleSearch() {
     const searchInput = document.querySelector("#search-input") as HTMLInputElement;
     const serverList = document.querySelector("#server-list") as HTMLElement;
@@ -136,19 +279,18 @@ async function handleSearch() {
     if (!searchInput || !serverList) return;
 
     try {
-        // If search input is empty, clear the list and show default message
+        // Without a search, list the favorites
         if (!searchInput.value.trim()) {
-            serverList.innerHTML = `
-                <div class="text-center text-base-content/60 p-4">
-                    Search for servers to connect
-                </div>
-            `;
+            await showFavorites(serverList);
             return;
         }
 
-        const results = await invoke<Host[]>("search_hosts", {
-            query: searchInput.value
+        const page = await invoke<SearchPage>("search_hosts", {
+            query: searchInput.value,
+            limit: SEARCH_PAGE_SIZE,
+            offset: 0
         });
+        const results = page.results;
 
         // Clear existing items
         serverList.innerHTML = "";
@@ -163,42 +305,21 @@ async function handleSearch() {
         }
 
         // Add new results
-        results.forEach(host => {
-            const item = document.createElement("div");
-            item.className = "flex items-center justify-between p-4 border-b border-base-300 last:border-b-0";
-            
-            item.innerHTML = `
-                <div class="flex flex-col">
-                    <span class="font-medium">${host.hostname}</span>
-                    <span class="text-sm opacity-70">${host.description}</span>
-                </div>
-                <button class="connect-btn btn btn-primary btn-sm">
-                    Connect
-                </button>
-            `;
+        results.forEach(host => serverList.appendChild(renderHost(host)));
 
-            // Add click handler for the connect button
-            const connectBtn = item.querySelector('.connect-btn');
-            if (connectBtn) {
-                connectBtn.addEventListener('click', async (e) => {
-                    e.stopPropagation();
-                    try {
-                        await invoke("launch_rdp", { host });
-                    } catch (err) {
-                        console.error("Failed to connect:", err);
-                    }
-                });
-            }
-
-            serverList.appendChild(item);
-        });
+        if (page.total > results.length) {
+            const more = document.createElement("div");
+            more.className = "text-center text-sm text-base-content/60 p-2";
+            more.textContent = `Showing ${results.length} of ${page.total} matches, refine the search to narrow them down`;
+            serverList.appendChild(more);
+        }
     } catch (err) {
         console.error("Search failed:", err);
-        serverList.innerHTML = `
-            <div class="text-center text-error p-4">
-                Failed to search hosts
-            </div>
-        `;
+        // A malformed query comes back as a message worth showing, e.g. a missing quote
+        const message = document.createElement("div");
+        message.className = "text-center text-error p-4";
+        message.textContent = typeof err === "string" ? err : "Failed to search hosts";
+        serverList.replaceChildren(message);
     }
 }
 
@@ -216,15 +337,9 @@ function initializeSearch() {
     }
 }
 
+// Starts out with the favorites, or a hint when there are none
 function initializeServerList() {
-    const serverList = document.querySelector("#server-list") as HTMLElement;
-    if (serverList) {
-        serverList.innerHTML = `
-            <div class="text-center text-base-content/60 p-4">
-                Search for servers to connect
-            </div>
-        `;
-    }
+    handleSearch();
 }
 
 // Modify the main DOMContentLoaded event listener
@@ -232,6 +347,11 @@ document.addEventListener("DOMContentLoaded", () => {
     initializeTheme();
     initializeSearch();
     initializeServerList();
+
+    // Refresh the current results when hosts.csv is edited outside ConnectX
+    listen("hosts-changed", () => {
+        handleSearch();
+    });
     
     // Get form elements
     const form = document.querySelector("#login-form") as HTMLFormElement | null;